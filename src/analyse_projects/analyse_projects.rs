@@ -1,43 +1,78 @@
-use std::path::PathBuf;
-
 use crossbeam::queue::SegQueue;
-use yansi::Color;
 
-use crate::output;
-use crate::Project;
-use crate::Settings;
+use crate::error::Result;
+use crate::utils::dir_sizes;
+use crate::{Event, Project, Scanner};
 
 use super::filter_by_modified_date::filter_by_modified_date;
+use super::filter_tracked_dirs::filter_tracked_dirs;
+use super::merge_shared_dirs::{find_dir_users, merge_shared_dirs};
+use super::prune_rust_targets::prune_rust_targets;
 
 /// Analyses a queue of projects loaded from `discover_projects()`
 ///
 /// # Arguments
 /// `projects` - The discovered projects
-/// `settings` - The application settings struct
+/// `scanner`  - Configures which projects can be cleaned. Cancelling its
+///              token stops analysing, leaving the results incomplete.
 ///
 /// # Returns
-/// All discovered cleanable directories
-pub fn analyse_projects(projects: SegQueue<Project>, settings: &Settings) -> Vec<PathBuf> {
-	let filtered = if settings.all {
-		output::println(
-			"Skip",
-			Color::Yellow,
-			"--all flag set, ignoring last used time",
-		);
+/// All projects that should be cleaned, sorted by their root directory,
+/// with the size of each cleanable directory filled in, or an error if
+/// analysing stopped unexpectedly
+pub fn analyse_projects(projects: SegQueue<Project>, scanner: &Scanner) -> Result<Vec<Project>> {
+	let users = find_dir_users(&projects);
+
+	if scanner.prune {
+		prune_rust_targets(&projects, scanner);
+	}
+
+	let filtered = if scanner.all {
 		projects
 	} else {
-		filter_by_modified_date(projects)
+		filter_by_modified_date(projects, scanner)?
 	};
 
-	if filtered.len() == 0 {
-		return Vec::new();
+	let mut projects = Vec::with_capacity(filtered.len());
+	while let Ok(project) = filtered.pop() {
+		projects.push(project);
 	}
 
-	let mut dirs = Vec::new();
-	while let Ok(project) = filtered.pop() {
-		dirs.append(&mut project.into_cleanable_dirs());
+	projects.sort_by(|a, b| a.root().cmp(b.root()));
+
+	filter_tracked_dirs(&mut projects, scanner);
+	merge_shared_dirs(&mut projects, &users, scanner);
+	projects.retain(|project| !project.cleanable_dirs().is_empty());
+
+	// Measure all directories in one go, so hard links shared between
+	// projects are only counted once
+	let paths: Vec<_> = projects
+		.iter()
+		.flat_map(|project| project.cleanable_dirs())
+		.flat_map(|dir| dir.targets())
+		.collect();
+	if !paths.is_empty() {
+		scanner.emit(Event::MeasureStarted {
+			directories: paths.len(),
+		});
+	}
+
+	let mut sizes = dir_sizes(&paths, &scanner.cancel, |event| scanner.emit(event))?.into_iter();
+
+	let mut dir_count = 0;
+	for project in &mut projects {
+		for dir in project.cleanable_dirs_mut() {
+			// A pruned directory is measured as the sum of its stale artifacts
+			dir.size = sizes.by_ref().take(dir.targets().len()).sum();
+			dir_count += 1;
+		}
+	}
+
+	if dir_count > 0 {
+		scanner.emit(Event::Measured {
+			directories: dir_count,
+		});
 	}
 
-	dirs.sort();
-	return dirs;
+	return Ok(projects);
 }
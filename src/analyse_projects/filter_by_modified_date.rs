@@ -1,40 +1,93 @@
 use std::cmp::max;
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crossbeam::queue::SegQueue;
-use yansi::Color;
 
-use crate::output;
+use crate::error::{Error, Result};
+use crate::project::Strategy;
 use crate::utils::file_utils::file_name;
 use crate::utils::process_queue;
-use crate::Project;
-
-const ALWAYS_IGNORE_DIRS: [&'static str; 3] = [".idea", ".vscode", ".git"];
-
-pub fn filter_by_modified_date(projects: SegQueue<Project>) -> SegQueue<Project> {
+use crate::{Event, Project, Scanner};
+
+const ALWAYS_IGNORE_DIRS: [&str; 3] = [".idea", ".vscode", ".git"];
+
+/// Removes the projects that were modified recently
+///
+/// Projects that can't be checked entirely are skipped, since they may
+/// contain recently modified files. Recent projects keep the directories
+/// that are only pruned.
+///
+/// # Arguments
+/// `projects` - The projects to check
+/// `scanner`  - Configures how old projects must be
+///
+/// # Returns
+/// The projects that can be cleaned, or an error if checking stopped
+/// unexpectedly
+pub fn filter_by_modified_date(
+	projects: SegQueue<Project>,
+	scanner: &Scanner,
+) -> Result<SegQueue<Project>> {
 	let old_projects = SegQueue::new();
 	let recent_projects = AtomicUsize::new(0);
+	let pruned_recent_projects = AtomicUsize::new(0);
+
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.unwrap_or(0);
 
 	process_queue(
 		max(2, num_cpus::get()),
 		&projects,
-		|project| {
+		&scanner.cancel,
+		|mut project| {
+			// Projects without an age threshold, like the global Go caches,
+			// can be cleaned regardless of when they were last modified
+			if project.ignores_age() {
+				old_projects.push(project);
+				return;
+			}
+
 			let paths = SegQueue::new();
 			let modified = SegQueue::new();
 
-			find_modified_date_of_directory(&project, project.root(), &paths, &modified);
+			// The first error that occured, after which the rest of the
+			// project doesn't need to be checked anymore
+			let error = Mutex::new(None);
+			let check = |path: &Path| {
+				if let Err(e) = find_modified_date_of_directory(&project, path, &paths, &modified) {
+					error.lock().unwrap().get_or_insert(e);
+				}
+			};
+
+			check(project.root());
 
-			process_queue(
+			let result = process_queue(
 				max(8, num_cpus::get() * 2),
 				&paths,
+				&scanner.cancel,
 				|path| {
-					output::print("Analysing", Color::Cyan, path.to_str().unwrap_or(""));
-					find_modified_date_of_directory(&project, &path, &paths, &modified);
+					if error.lock().unwrap().is_some() {
+						return;
+					}
+
+					scanner.emit(Event::Analysing(&path));
+					check(&path);
 				},
-				|_| (),
 			);
 
+			if let Some(error) = error.into_inner().unwrap().or(result.err()) {
+				scanner.emit(Event::ProjectSkipped {
+					root: project.root(),
+					error: &error,
+				});
+				return;
+			}
+
 			let time_since_last_modified = {
 				let mut vec = Vec::with_capacity(modified.len());
 				while let Ok(m) = modified.pop() {
@@ -46,58 +99,64 @@ pub fn filter_by_modified_date(projects: SegQueue<Project>) -> SegQueue<Project>
 				vec.into_iter().min().unwrap_or(0)
 			};
 
-			// Count the total size (depending on modified date)
-			if time_since_last_modified > 2_592_000 {
+			project.set_last_modified(now.saturating_sub(time_since_last_modified));
+
+			// A threshold in the project's .swpfile overrides the global one
+			let max_age = project.max_age().unwrap_or(scanner.older_than);
+
+			if time_since_last_modified > max_age.as_secs() {
 				old_projects.push(project);
 			} else {
 				recent_projects.fetch_add(1, Ordering::SeqCst);
+				scanner.emit(Event::ProjectRecent(&project));
+
+				// Pruning only removes stale artifacts, so it's safe in
+				// projects that are still being worked on
+				project.retain_cleanable_dirs(|dir| dir.strategy != Strategy::Remove);
+				if !project.cleanable_dirs().is_empty() {
+					pruned_recent_projects.fetch_add(1, Ordering::SeqCst);
+					old_projects.push(project);
+				}
 			}
 		},
-		|tries| {
-			output::print("Analysing", Color::Cyan, &".".repeat(tries));
-		},
-	);
+	)?;
 
-	let recent_project_count = recent_projects.into_inner();
-	let old_project_count = old_projects.len();
-
-	if recent_project_count == 0 {
-		output::println("Analysed", Color::Green, "All projects can be cleaned");
-	} else if old_project_count == 0 {
-		output::println(
-			"Analysed",
-			Color::Green,
-			"All projects have been modified recently",
-		);
-	} else {
-		let message = format!(
-			"{} of {} projects can be cleaned",
-			old_project_count,
-			old_project_count + recent_project_count
-		);
-		output::println("Analysed", Color::Green, &message);
-		output::println_info(format!(
-			"{} projects have been modified recently",
-			recent_project_count
-		));
-	}
+	scanner.emit(Event::Analysed {
+		cleanable: old_projects.len() - pruned_recent_projects.into_inner(),
+		recent: recent_projects.into_inner(),
+	});
 
-	return old_projects;
+	return Ok(old_projects);
 }
 
+/// Finds when the files in a directory were last modified, and queues its
+/// subdirectories to be checked as well
+///
+/// # Arguments
+/// `project`  - The project the directory is in
+/// `path`     - The directory to check
+/// `paths`    - Subdirectories that need to be checked will be added to this queue
+/// `modified` - The time since the most recently modified file in the
+///              directory will be added to this queue, in seconds
+///
+/// # Returns
+/// An error if the directory could not be read
 fn find_modified_date_of_directory(
 	project: &Project,
 	path: &Path,
 	paths: &SegQueue<PathBuf>,
 	modified: &SegQueue<Option<u64>>,
-) {
+) -> Result<()> {
 	let (dirs, files) = {
 		let mut dirs = Vec::new();
 		let mut files = Vec::new();
 
-		let read_dir = path.read_dir().expect("Could not read directory"); // TODO add better error handling
-		for entry in read_dir.filter_map(|e| e.ok()) {
-			if entry.file_type().unwrap().is_dir() {
+		let read_dir = path.read_dir().map_err(|e| Error::io(path, e))?;
+		for entry in read_dir {
+			let entry = entry.map_err(|e| Error::io(path, e))?;
+			let file_type = entry.file_type().map_err(|e| Error::io(entry.path(), e))?;
+
+			if file_type.is_dir() {
 				dirs.push(entry.path());
 			} else {
 				files.push(entry);
@@ -127,4 +186,6 @@ fn find_modified_date_of_directory(
 
 		paths.push(dir);
 	}
+
+	return Ok(());
 }
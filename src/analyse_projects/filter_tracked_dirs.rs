@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::git::{find_repository, GitIndex};
+use crate::{Event, Project, Scanner, SkipReason};
+
+/// Removes cleanable directories that contain files tracked by git
+///
+/// Some projects commit their `build` or `dist` directory on purpose, so
+/// deleting it would remove files from the repository. If the git index of a
+/// repository can't be read, none of its directories are cleaned.
+///
+/// # Arguments
+/// `projects` - The projects to check
+/// `scanner`  - Reports the directories that are kept
+pub fn filter_tracked_dirs(projects: &mut [Project], scanner: &Scanner) {
+	// Several projects can be in the same repository, so each index is only
+	// read once, even if it can't be read
+	let mut indexes: HashMap<PathBuf, Result<GitIndex>> = HashMap::new();
+
+	for project in projects.iter_mut() {
+		project.retain_cleanable_dirs(|dir| {
+			let parent = match dir.path.parent() {
+				Some(parent) => parent,
+				None => return true,
+			};
+
+			let (root, git_dir) = match find_repository(parent) {
+				Ok(Some(repository)) => repository,
+				Ok(None) => return true,
+				Err(e) => {
+					scanner.emit(Event::Skipped {
+						path: &dir.path,
+						reason: SkipReason::GitFailed(&Error::io(parent, e)),
+					});
+					return false;
+				}
+			};
+
+			let index = indexes.entry(root).or_insert_with_key(|root| {
+				GitIndex::read(root, &git_dir).map_err(|e| Error::io(git_dir.join("index"), e))
+			});
+
+			let reason = match index {
+				Ok(index) if !index.tracks_files_in(&dir.path) => return true,
+				Ok(_) => SkipReason::TrackedByGit,
+				Err(e) => SkipReason::GitFailed(e),
+			};
+
+			scanner.emit(Event::Skipped {
+				path: &dir.path,
+				reason,
+			});
+			return false;
+		});
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::process::Command;
+
+	use super::*;
+	use crate::project::Reason;
+	use crate::utils::test_utils;
+
+	/// Runs a git command in the given directory, which requires git to be
+	/// installed
+	fn git(dir: &std::path::Path, args: &[&str]) {
+		let status = Command::new("git")
+			.args(args)
+			.current_dir(dir)
+			.output()
+			.expect("Could not run git")
+			.status;
+		assert!(status.success(), "git {} failed", args.join(" "));
+	}
+
+	#[test]
+	fn skip_tracked() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "build");
+			test_utils::create_file(dir, "build/index.html");
+			test_utils::create_dir(dir, "node_modules");
+			test_utils::create_file(dir, "node_modules/index.js");
+			test_utils::create_file(dir, "package.json");
+
+			git(dir, &["init", "-q"]);
+			git(dir, &["add", "build", "package.json"]);
+
+			let reason = Reason::marker("package.json");
+			let mut project = Project::new(dir);
+			project.add_cleanable_dir_if_exists("build", &reason);
+			project.add_cleanable_dir_if_exists("node_modules", &reason);
+
+			let mut projects = vec![project];
+			filter_tracked_dirs(&mut projects, &Scanner::new());
+
+			assert!(!projects[0].is_cleanable_dir(dir.join("build")));
+			assert!(projects[0].is_cleanable_dir(dir.join("node_modules")));
+		});
+	}
+
+	#[test]
+	fn skip_tracked_split_index() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "build");
+			test_utils::create_file(dir, "build/index.html");
+			test_utils::create_dir(dir, "dist");
+			test_utils::create_file(dir, "dist/index.js");
+			test_utils::create_dir(dir, "node_modules");
+			test_utils::create_file(dir, "node_modules/index.js");
+
+			// Files added after splitting the index are only in the split
+			// index, the others are in the shared index
+			git(dir, &["init", "-q"]);
+			git(dir, &["add", "build"]);
+			git(dir, &["update-index", "--split-index"]);
+			git(dir, &["add", "dist"]);
+
+			let reason = Reason::marker("package.json");
+			let mut project = Project::new(dir);
+			project.add_cleanable_dir_if_exists("build", &reason);
+			project.add_cleanable_dir_if_exists("dist", &reason);
+			project.add_cleanable_dir_if_exists("node_modules", &reason);
+
+			let mut projects = vec![project];
+			filter_tracked_dirs(&mut projects, &Scanner::new());
+
+			assert!(!projects[0].is_cleanable_dir(dir.join("build")));
+			assert!(!projects[0].is_cleanable_dir(dir.join("dist")));
+			assert!(projects[0].is_cleanable_dir(dir.join("node_modules")));
+		});
+	}
+}
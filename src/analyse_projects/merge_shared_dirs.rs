@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crossbeam::queue::SegQueue;
+
+use crate::{Event, Project, Scanner, SkipReason};
+
+/// Maps each cleanable directory to the roots of all projects that use it
+pub type DirUsers = HashMap<PathBuf, Vec<PathBuf>>;
+
+/// Finds which projects use each cleanable directory
+///
+/// This must be called before projects are filtered, so a directory shared
+/// with a recently used project is known to be in use.
+///
+/// # Arguments
+/// `projects` - All discovered projects
+pub fn find_dir_users(projects: &SegQueue<Project>) -> DirUsers {
+	let mut users = DirUsers::new();
+
+	let mut all = Vec::with_capacity(projects.len());
+	while let Ok(project) = projects.pop() {
+		all.push(project);
+	}
+
+	for project in all {
+		for dir in project.cleanable_dirs() {
+			users
+				.entry(dir.path.clone())
+				.or_default()
+				.push(project.root().to_owned());
+		}
+		projects.push(project);
+	}
+
+	return users;
+}
+
+/// Makes sure directories shared between projects, like a Cargo target
+/// directory used by several workspaces, are only listed once
+///
+/// A shared directory is only kept if all projects using it are going to be
+/// cleaned. It's listed under the first of those projects, and the other
+/// projects are recorded in its `shared_with` list.
+///
+/// # Arguments
+/// `projects` - The projects to clean, sorted by root directory
+/// `users`    - Result of `find_dir_users` before filtering the projects
+/// `scanner`  - Reports the directories that are kept
+pub fn merge_shared_dirs(projects: &mut [Project], users: &DirUsers, scanner: &Scanner) {
+	let roots: Vec<PathBuf> = projects.iter().map(|p| p.root().to_owned()).collect();
+	let mut listed: Vec<PathBuf> = Vec::new();
+
+	for project in projects.iter_mut() {
+		project.retain_cleanable_dirs(|dir| {
+			if listed.contains(&dir.path) {
+				return false;
+			}
+
+			let recent_user = users
+				.get(&dir.path)
+				.and_then(|users| users.iter().find(|root| !roots.contains(root)));
+			if let Some(root) = recent_user {
+				scanner.emit(Event::Skipped {
+					path: &dir.path,
+					reason: SkipReason::UsedBy(root),
+				});
+				return false;
+			}
+
+			return true;
+		});
+
+		let root = project.root().to_owned();
+		for dir in project.cleanable_dirs_mut() {
+			if let Some(users) = users.get(&dir.path) {
+				dir.shared_with = users.iter().filter(|r| **r != root).cloned().collect();
+			}
+			listed.push(dir.path.clone());
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::project::Reason;
+	use crate::utils::test_utils;
+
+	#[test]
+	fn shared_target_dir() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "b");
+			test_utils::create_dir(dir, "c");
+			test_utils::create_dir(dir, "target");
+			test_utils::create_dir(dir, "target-c");
+
+			let reason = Reason::marker("Cargo.toml");
+			let queue = SegQueue::new();
+			for (name, target) in [("a", "target"), ("b", "target"), ("c", "target-c")].iter() {
+				let mut project = Project::new(dir.join(name));
+				project.add_external_dir_if_exists(dir.join(target), &reason);
+				queue.push(project);
+			}
+
+			let users = find_dir_users(&queue);
+			let mut projects = Vec::new();
+			while let Ok(project) = queue.pop() {
+				projects.push(project);
+			}
+			projects.sort_by(|a, b| a.root().cmp(b.root()));
+
+			merge_shared_dirs(&mut projects, &users, &Scanner::new());
+			assert_eq!(
+				projects[0].cleanable_dirs()[0].shared_with,
+				vec![dir.join("b")]
+			);
+			assert!(projects[1].cleanable_dirs().is_empty());
+			assert_eq!(projects[2].cleanable_dirs().len(), 1);
+
+			// Project b was used recently, so the shared directory is kept
+			let mut projects: Vec<Project> = projects
+				.into_iter()
+				.filter(|p| p.root() != dir.join("b"))
+				.collect();
+			for project in projects.iter_mut() {
+				project.add_external_dir_if_exists(dir.join("target"), &reason);
+			}
+			merge_shared_dirs(&mut projects, &users, &Scanner::new());
+			assert!(!projects[0].is_cleanable_dir(dir.join("target")));
+		});
+	}
+}
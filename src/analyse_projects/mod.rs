@@ -1,4 +1,7 @@
 mod analyse_projects;
 mod filter_by_modified_date;
+mod filter_tracked_dirs;
+mod merge_shared_dirs;
+mod prune_rust_targets;
 
 pub use self::analyse_projects::analyse_projects;
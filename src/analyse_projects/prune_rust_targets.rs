@@ -0,0 +1,491 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crossbeam::queue::SegQueue;
+
+use crate::discover_projects::{find_target_dir, find_workspace_root};
+use crate::project::Strategy;
+use crate::{Project, Scanner};
+
+/// Switches the target directory of Rust projects to only deleting stale
+/// build artifacts, instead of the entire directory
+///
+/// Target directories that don't contain anything stale are removed from
+/// their project.
+///
+/// # Arguments
+/// `projects` - The discovered projects
+/// `scanner`  - Configures which projects can be cleaned
+pub fn prune_rust_targets(projects: &SegQueue<Project>, scanner: &Scanner) {
+	let mut all = Vec::with_capacity(projects.len());
+	while let Ok(project) = projects.pop() {
+		all.push(project);
+	}
+
+	for mut project in all {
+		if project.ecosystems().iter().any(|e| e == "rust") {
+			let workspace_root = find_workspace_root(project.root());
+			let target_dir = find_target_dir(project.root(), &workspace_root, &scanner.cargo);
+			let max_age = project.max_age().unwrap_or(scanner.older_than);
+
+			// Crates are only removed based on the lock file if the target
+			// directory isn't shared with other workspaces
+			let lock_file = if target_dir == workspace_root.join("target") {
+				Some(workspace_root.join("Cargo.lock"))
+			} else {
+				None
+			};
+
+			for dir in project.cleanable_dirs_mut() {
+				// Pruning would delete files in the directory the symlink
+				// points to, instead of just the link
+				if dir.path == target_dir && !dir.is_symlink() {
+					let stale = find_stale_artifacts(&dir.path, lock_file.as_deref(), max_age);
+					dir.strategy = Strategy::Prune(stale);
+				}
+			}
+
+			project.retain_cleanable_dirs(|dir| dir.strategy != Strategy::Prune(Vec::new()));
+		}
+
+		projects.push(project);
+	}
+}
+
+/// Finds the stale build artifacts in a Rust target directory
+///
+/// These are:
+/// - `incremental` directories, which only speed up rebuilds after changes
+/// - Profiles (like `debug` or `release`) that weren't built within `max_age`
+/// - Artifacts of crates that are no longer in `Cargo.lock`
+/// - Artifacts built by a different version of rustc than the current one
+///
+/// # Arguments
+/// `target_dir` - The target directory
+/// `lock_file`  - The workspace's `Cargo.lock`, if crates that are no longer
+///                used should be removed
+/// `max_age`    - Profiles that weren't built for this long are removed
+///
+/// # Returns
+/// The files and directories that can be deleted
+pub fn find_stale_artifacts(
+	target_dir: &Path,
+	lock_file: Option<&Path>,
+	max_age: Duration,
+) -> Vec<PathBuf> {
+	let locked_crates = lock_file.and_then(read_locked_crates);
+	let cutoff = SystemTime::now().checked_sub(max_age);
+
+	let profiles: Vec<(PathBuf, Vec<Unit>)> = find_profiles(target_dir)
+		.into_iter()
+		.map(|profile| {
+			let units = units(&profile);
+			(profile, units)
+		})
+		.collect();
+	let current_rustc = current_rustc_hash(target_dir, &profiles);
+
+	let mut stale = Vec::new();
+
+	for (profile, units) in profiles {
+		let last_built = units.iter().filter_map(|unit| unit.built).max();
+		if let (Some(last_built), Some(cutoff)) = (last_built, cutoff) {
+			if last_built < cutoff {
+				stale.push(profile);
+				continue;
+			}
+		}
+
+		let incremental = profile.join("incremental");
+		if incremental.is_dir() {
+			stale.push(incremental);
+		}
+
+		let stale_units: HashSet<String> = units
+			.into_iter()
+			.filter(|unit| {
+				let unlocked = locked_crates
+					.as_ref()
+					.map(|locked| !locked.contains(&unit.package))
+					.unwrap_or(false);
+
+				let other_rustc = match (current_rustc, unit.rustc) {
+					(Some(current), Some(rustc)) => rustc != current,
+					_ => false,
+				};
+
+				unlocked || other_rustc
+			})
+			.map(|unit| unit.hash)
+			.collect();
+
+		if stale_units.is_empty() {
+			continue;
+		}
+
+		// All artifacts of a unit end in the same hash, e.g. `deps/libserde-<hash>.rlib`
+		for subdir in [".fingerprint", "build", "deps"].iter() {
+			let entries = match profile.join(subdir).read_dir() {
+				Ok(entries) => entries,
+				Err(_) => continue,
+			};
+
+			for entry in entries.filter_map(|e| e.ok()) {
+				let name = entry.file_name();
+				let hash = artifact_hash(&name.to_string_lossy()).map(str::to_owned);
+				if hash.map(|h| stale_units.contains(&h)).unwrap_or(false) {
+					stale.push(entry.path());
+				}
+			}
+		}
+	}
+
+	stale.sort();
+	return stale;
+}
+
+/// A single compilation unit in a profile, identified by its fingerprint
+struct Unit {
+	/// Name of the package the unit belongs to
+	package: String,
+
+	/// The hash at the end of all of the unit's artifacts
+	hash: String,
+
+	/// Hash of the rustc version that built the unit
+	rustc: Option<u64>,
+
+	/// The last time the unit was compiled
+	built: Option<SystemTime>,
+}
+
+/// Finds the profile directories in a target directory, e.g. `target/debug`
+/// and `target/x86_64-unknown-linux-gnu/release`
+fn find_profiles(target_dir: &Path) -> Vec<PathBuf> {
+	let mut profiles = Vec::new();
+
+	let subdirs = |dir: &Path| -> Vec<PathBuf> {
+		dir.read_dir()
+			.map(|entries| {
+				entries
+					.filter_map(|e| e.ok())
+					.filter(|e| e.file_type().map(|t| t.is_dir()).unwrap_or(false))
+					.map(|e| e.path())
+					.collect()
+			})
+			.unwrap_or_default()
+	};
+
+	for dir in subdirs(target_dir) {
+		if dir.join(".fingerprint").is_dir() {
+			profiles.push(dir);
+			continue;
+		}
+
+		// Cross-compiled profiles are nested in a directory named after the
+		// target triple
+		for nested in subdirs(&dir) {
+			if nested.join(".fingerprint").is_dir() {
+				profiles.push(nested);
+			}
+		}
+	}
+
+	profiles.sort();
+	return profiles;
+}
+
+/// Gets the last time a unit was compiled, from the files in its
+/// fingerprint directory like `invoked.timestamp` and `dep-lib-<name>`
+///
+/// The directory itself is only modified when files are added or removed,
+/// not when Cargo rewrites the files in it.
+fn last_built(unit_dir: &Path) -> Option<SystemTime> {
+	let entries = unit_dir.read_dir().ok()?;
+
+	return entries
+		.filter_map(|e| e.ok())
+		.filter_map(|e| e.metadata().ok())
+		.filter_map(|m| m.modified().ok())
+		.max();
+}
+
+/// Reads the compilation units in a profile from its `.fingerprint` directory
+fn units(profile: &Path) -> Vec<Unit> {
+	let entries = match profile.join(".fingerprint").read_dir() {
+		Ok(entries) => entries,
+		Err(_) => return Vec::new(),
+	};
+
+	let mut units = Vec::new();
+	for entry in entries.filter_map(|e| e.ok()) {
+		let name = entry.file_name().to_string_lossy().into_owned();
+		let (package, hash) = match name.rfind('-') {
+			Some(i) => (name[..i].to_owned(), name[i + 1..].to_owned()),
+			None => continue,
+		};
+
+		units.push(Unit {
+			package,
+			hash,
+			rustc: read_fingerprint_rustc(&entry.path()),
+			built: last_built(&entry.path()),
+		});
+	}
+
+	return units;
+}
+
+/// Reads the rustc version hash from the JSON files in a unit's fingerprint
+/// directory
+fn read_fingerprint_rustc(unit_dir: &Path) -> Option<u64> {
+	let entries = unit_dir.read_dir().ok()?;
+
+	for entry in entries.filter_map(|e| e.ok()) {
+		if entry
+			.path()
+			.extension()
+			.map(|e| e != "json")
+			.unwrap_or(true)
+		{
+			continue;
+		}
+
+		let json: serde_json::Value = match fs::read(entry.path())
+			.ok()
+			.and_then(|data| serde_json::from_slice(&data).ok())
+		{
+			Some(json) => json,
+			None => continue,
+		};
+
+		if let Some(rustc) = json.get("rustc").and_then(|r| r.as_u64()) {
+			return Some(rustc);
+		}
+	}
+
+	return None;
+}
+
+/// Finds the rustc version hash of the current toolchain, from
+/// `.rustc_info.json` in the target directory
+///
+/// Cargo caches the output of rustc in this file, and rewrites it whenever
+/// the toolchain changes. The fingerprint in it identifies the rustc binary
+/// rather than the version hash stored with each unit, so the current hash
+/// is taken from the units that were built after the file was written.
+///
+/// # Arguments
+/// `target_dir` - The target directory
+/// `profiles`   - The profiles in the target directory, with their units
+///
+/// # Returns
+/// The version hash, or None if it can't be determined, e.g. because the
+/// file is missing or nothing was built since the toolchain changed
+fn current_rustc_hash(target_dir: &Path, profiles: &[(PathBuf, Vec<Unit>)]) -> Option<u64> {
+	let rustc_info = target_dir.join(".rustc_info.json");
+	let json: serde_json::Value = serde_json::from_slice(&fs::read(&rustc_info).ok()?).ok()?;
+	json.get("rustc_fingerprint")?.as_u64()?;
+
+	let written = fs::metadata(&rustc_info).and_then(|m| m.modified()).ok()?;
+
+	return profiles
+		.iter()
+		.flat_map(|(_, units)| units)
+		.filter_map(|unit| Some((unit.rustc?, unit.built?)))
+		.filter(|(_, built)| *built >= written)
+		.max_by_key(|(_, built)| *built)
+		.map(|(rustc, _)| rustc);
+}
+
+/// Reads the names of all packages in a `Cargo.lock` file
+fn read_locked_crates(lock_file: &Path) -> Option<HashSet<String>> {
+	let contents = fs::read_to_string(lock_file).ok()?;
+	let lock: toml::Value = toml::from_str(&contents).ok()?;
+
+	let packages = lock.get("package")?.as_array()?;
+	return Some(
+		packages
+			.iter()
+			.filter_map(|p| p.get("name"))
+			.filter_map(|n| n.as_str())
+			.map(str::to_owned)
+			.collect(),
+	);
+}
+
+/// Gets the unit hash from the name of a build artifact, e.g. `abc123` from
+/// `libserde-abc123.rlib`
+fn artifact_hash(name: &str) -> Option<&str> {
+	// Crate names can't contain dots, so everything after the first one is
+	// the file extension
+	let stem = name.split('.').next()?;
+	let hash = &stem[stem.rfind('-')? + 1..];
+
+	if hash.is_empty() || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+		return None;
+	}
+
+	return Some(hash);
+}
+
+#[cfg(test)]
+mod test {
+	use std::fs::File;
+
+	use super::*;
+	use crate::utils::test_utils;
+
+	const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+	/// Creates the fingerprint and artifacts of a unit in a profile
+	fn create_unit(profile: &Path, package: &str, hash: &str, rustc: u64) {
+		let fingerprint = profile
+			.join(".fingerprint")
+			.join(format!("{}-{}", package, hash));
+		fs::create_dir_all(&fingerprint).expect("Could not create test directory");
+		fs::write(
+			fingerprint.join(format!("lib-{}.json", package)),
+			format!("{{\"rustc\":{}}}", rustc),
+		)
+		.expect("Could not write test file");
+
+		let crate_name = package.replace('-', "_");
+		fs::create_dir_all(profile.join("deps")).expect("Could not create test directory");
+		File::create(profile.join(format!("deps/lib{}-{}.rlib", crate_name, hash)))
+			.expect("Could not write test file");
+		File::create(profile.join(format!("deps/{}-{}.d", crate_name, hash)))
+			.expect("Could not write test file");
+	}
+
+	/// Writes the rustc cache Cargo creates in a target directory
+	fn write_rustc_info(target_dir: &Path) {
+		fs::write(
+			target_dir.join(".rustc_info.json"),
+			"{\"rustc_fingerprint\":42,\"outputs\":{},\"successes\":{}}",
+		)
+		.expect("Could not write test file");
+	}
+
+	#[test]
+	fn hashes() {
+		assert_eq!(artifact_hash("libserde-0a1b2c.rlib"), Some("0a1b2c"));
+		assert_eq!(artifact_hash("proc_macro2-0a1b2c.d"), Some("0a1b2c"));
+		assert_eq!(artifact_hash("my-tool-0a1b2c"), Some("0a1b2c"));
+		assert_eq!(artifact_hash("my-tool"), None);
+		assert_eq!(artifact_hash("libserde.rlib"), None);
+	}
+
+	#[test]
+	fn incremental_and_unlocked() {
+		test_utils::with_temp_dir(|dir| {
+			let profile = dir.join("target/debug");
+			create_unit(&profile, "serde", "aaaa", 1);
+			create_unit(&profile, "old-crate", "bbbb", 1);
+			fs::create_dir_all(profile.join("incremental/app-cccc"))
+				.expect("Could not create test directory");
+			fs::write(
+				dir.join("Cargo.lock"),
+				"[[package]]\nname = \"serde\"\nversion = \"1.0.0\"",
+			)
+			.expect("Could not write test file");
+
+			let stale =
+				find_stale_artifacts(&dir.join("target"), Some(&dir.join("Cargo.lock")), DAY);
+
+			assert_eq!(
+				stale,
+				vec![
+					profile.join(".fingerprint/old-crate-bbbb"),
+					profile.join("deps/libold_crate-bbbb.rlib"),
+					profile.join("deps/old_crate-bbbb.d"),
+					profile.join("incremental"),
+				]
+			);
+
+			// Without a lock file, only the incremental directory is stale
+			let stale = find_stale_artifacts(&dir.join("target"), None, DAY);
+			assert_eq!(stale, vec![profile.join("incremental")]);
+		});
+	}
+
+	#[test]
+	fn other_rustc() {
+		test_utils::with_temp_dir(|dir| {
+			let profile = dir.join("target/release");
+			create_unit(&profile, "old", "aaaa", 1);
+
+			// Without .rustc_info.json, the current version isn't known
+			let stale = find_stale_artifacts(&dir.join("target"), None, DAY);
+			assert_eq!(stale, Vec::<PathBuf>::new());
+
+			// Wait for the next timestamp, so the units built after the
+			// toolchain changed are clearly newer
+			std::thread::sleep(Duration::from_millis(20));
+			write_rustc_info(&dir.join("target"));
+			std::thread::sleep(Duration::from_millis(20));
+
+			// Nothing was built with the new toolchain yet
+			let stale = find_stale_artifacts(&dir.join("target"), None, DAY);
+			assert_eq!(stale, Vec::<PathBuf>::new());
+
+			create_unit(&profile, "new", "bbbb", 2);
+
+			let stale = find_stale_artifacts(&dir.join("target"), None, DAY);
+			assert_eq!(
+				stale,
+				vec![
+					profile.join(".fingerprint/old-aaaa"),
+					profile.join("deps/libold-aaaa.rlib"),
+					profile.join("deps/old-aaaa.d"),
+				]
+			);
+		});
+	}
+
+	#[test]
+	fn rebuilt_unit() {
+		test_utils::with_temp_dir(|dir| {
+			let profile = dir.join("target/debug");
+			create_unit(&profile, "app", "aaaa", 1);
+
+			// Cargo rewrites the files of a unit when it's rebuilt, which
+			// doesn't change the modified time of the unit's directory
+			std::thread::sleep(Duration::from_millis(300));
+			fs::write(
+				profile.join(".fingerprint/app-aaaa/lib-app.json"),
+				"{\"rustc\":1}",
+			)
+			.expect("Could not write test file");
+
+			let stale = find_stale_artifacts(&dir.join("target"), None, Duration::from_millis(150));
+			assert_eq!(stale, Vec::<PathBuf>::new());
+		});
+	}
+
+	#[test]
+	fn old_profile() {
+		test_utils::with_temp_dir(|dir| {
+			create_unit(&dir.join("target/debug"), "app", "aaaa", 1);
+			create_unit(
+				&dir.join("target/wasm32-unknown-unknown/release"),
+				"app",
+				"bbbb",
+				1,
+			);
+			test_utils::create_dir(dir, "target/doc");
+
+			let stale = find_stale_artifacts(&dir.join("target"), None, Duration::from_secs(0));
+
+			assert_eq!(
+				stale,
+				vec![
+					dir.join("target/debug"),
+					dir.join("target/wasm32-unknown-unknown/release"),
+				]
+			);
+		});
+	}
+}
@@ -0,0 +1,233 @@
+use crate::project::CleanableDir;
+use crate::trash::{self, move_to_trash};
+use crate::utils::{dir_sizes, remove_all, CancellationToken, RemoveErrors};
+
+/// Deletes cleanable directories, or moves them to the trash
+///
+/// Directories are cleaned one at a time, so the caller can stop between
+/// directories without leaving one half deleted.
+///
+/// # Example
+/// ```no_run
+/// use swp::{Cleaner, Scanner};
+///
+/// let projects = Scanner::new().path("/home/me/projects").scan()?;
+/// let dirs: Vec<_> = projects.iter().flat_map(|p| p.cleanable_dirs()).collect();
+///
+/// let cleaned = Cleaner::new().trash(true).clean_all(&dirs, |dir, deletion| {
+///     if let Some(errors) = &deletion.errors {
+///         eprintln!("Could not clean {}: {}", dir.path.display(), errors);
+///     }
+/// });
+/// println!("Moved {} bytes to the trash", cleaned.freed());
+/// # Ok::<(), swp::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Cleaner {
+	trash: bool,
+	cancel: CancellationToken,
+}
+
+/// The outcome of cleaning several directories with `Cleaner::clean_all`
+#[derive(Debug, Default)]
+pub struct Cleaned<'a> {
+	/// Each directory that was cleaned, and the outcome
+	pub results: Vec<(&'a CleanableDir, Deletion)>,
+
+	/// Directories that weren't cleaned because cleaning was cancelled
+	pub skipped: Vec<&'a CleanableDir>,
+}
+
+/// The outcome of cleaning a single directory
+#[derive(Debug)]
+pub struct Deletion {
+	/// Number of bytes freed (or moved to the trash), which may be more than
+	/// 0 even if the directory could not be cleaned entirely
+	pub freed: u64,
+
+	/// Everything that couldn't be deleted, if anything
+	pub errors: Option<RemoveErrors>,
+}
+
+impl Cleaner {
+	pub fn new() -> Cleaner {
+		Cleaner::default()
+	}
+
+	/// Checks if directories can be moved to the trash on this platform
+	///
+	/// Only the FreeDesktop.org trash is supported at this time, so cleaning
+	/// with `trash(true)` fails for every directory on Windows and macOS.
+	pub fn can_trash() -> bool {
+		trash::SUPPORTED
+	}
+
+	/// Moves directories to the trash instead of deleting them permanently
+	pub fn trash(mut self, trash: bool) -> Cleaner {
+		self.trash = trash;
+		self
+	}
+
+	/// Sets the token that stops `clean_all` when cancelled
+	pub fn cancel_token(mut self, cancel: CancellationToken) -> Cleaner {
+		self.cancel = cancel;
+		self
+	}
+
+	/// Cleans several directories one at a time
+	///
+	/// A directory that can't be cleaned doesn't stop the others from being
+	/// cleaned. When the cancel token is cancelled, the directory that is
+	/// being cleaned is finished first and the rest are skipped.
+	///
+	/// # Arguments
+	/// `dirs`       - The directories to clean
+	/// `on_cleaned` - Called after cleaning each directory, e.g. to show
+	///                progress
+	///
+	/// # Returns
+	/// The outcome of each directory, and the directories that were skipped
+	pub fn clean_all<'a, F: FnMut(&CleanableDir, &Deletion)>(
+		&self,
+		dirs: &[&'a CleanableDir],
+		mut on_cleaned: F,
+	) -> Cleaned<'a> {
+		let mut cleaned = Cleaned::default();
+
+		for dir in dirs {
+			if self.cancel.is_cancelled() {
+				cleaned.skipped.push(dir);
+				continue;
+			}
+
+			let deletion = self.clean(dir);
+			on_cleaned(dir, &deletion);
+			cleaned.results.push((dir, deletion));
+		}
+
+		return cleaned;
+	}
+
+	/// Deletes a cleanable directory, or the stale artifacts in it
+	///
+	/// When pruning, the remaining artifacts are still deleted if one fails.
+	/// The directory should have been measured, so the freed space can be
+	/// calculated.
+	///
+	/// # Arguments
+	/// `dir` - The directory to clean
+	///
+	/// # Returns
+	/// How much space was freed, and everything that couldn't be deleted
+	pub fn clean(&self, dir: &CleanableDir) -> Deletion {
+		let mut errors = RemoveErrors::default();
+
+		for path in dir.targets() {
+			if self.trash {
+				if let Err(e) = move_to_trash(&path) {
+					errors.failed.push((path, e));
+				}
+			} else if let Err(e) = remove_all(&path) {
+				errors.not_owned.extend(e.not_owned);
+				errors.failed.extend(e.failed);
+			}
+		}
+
+		if errors.is_empty() {
+			return Deletion {
+				freed: dir.size,
+				errors: None,
+			};
+		}
+
+		// Part of the directory may already have been deleted. If that can't
+		// be measured, nothing is reported as freed.
+		let remaining: u64 = dir_sizes(&dir.targets(), &CancellationToken::new(), |_| ())
+			.map(|sizes| sizes.iter().sum())
+			.unwrap_or(dir.size);
+
+		return Deletion {
+			freed: dir.size.saturating_sub(remaining),
+			errors: Some(errors),
+		};
+	}
+}
+
+impl Cleaned<'_> {
+	/// Number of bytes freed (or moved to the trash), including the parts of
+	/// directories that could only be cleaned partially
+	pub fn freed(&self) -> u64 {
+		self.results
+			.iter()
+			.map(|(_, deletion)| deletion.freed)
+			.sum()
+	}
+
+	/// Number of directories that were cleaned entirely
+	pub fn cleaned(&self) -> usize {
+		self.results.len() - self.failed()
+	}
+
+	/// Number of directories that could not be cleaned entirely
+	pub fn failed(&self) -> usize {
+		self.results
+			.iter()
+			.filter(|(_, deletion)| deletion.errors.is_some())
+			.count()
+	}
+
+	/// Checks if every directory was cleaned entirely
+	pub fn is_complete(&self) -> bool {
+		self.failed() == 0 && self.skipped.is_empty()
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::path::PathBuf;
+
+	use super::*;
+	use crate::project::{Reason, Strategy};
+	use crate::utils::test_utils;
+
+	fn cleanable_dir(path: PathBuf, size: u64) -> CleanableDir {
+		CleanableDir {
+			path,
+			reason: Reason::marker("package.json"),
+			size,
+			shared_with: Vec::new(),
+			strategy: Strategy::Remove,
+		}
+	}
+
+	#[test]
+	fn clean_all() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "b");
+			test_utils::create_dir(dir, "c");
+
+			let a = cleanable_dir(dir.join("a"), 1024);
+			let b = cleanable_dir(dir.join("b"), 2048);
+			let c = cleanable_dir(dir.join("c"), 4096);
+
+			// Cancelling stops after the directory that is being cleaned
+			let cancel = CancellationToken::new();
+			let cleaner = Cleaner::new().cancel_token(cancel.clone());
+			let cleaned = cleaner.clean_all(&[&a, &b, &c], |_, _| cancel.cancel());
+
+			assert!(!dir.join("a").exists());
+			assert!(dir.join("b").exists());
+			assert_eq!(cleaned.results.len(), 1);
+			assert_eq!(cleaned.skipped.len(), 2);
+			assert_eq!(cleaned.cleaned(), 1);
+			assert_eq!(cleaned.failed(), 0);
+			assert_eq!(cleaned.freed(), 1024);
+			assert!(!cleaned.is_complete());
+
+			let cleaned = Cleaner::new().clean_all(&[&b, &c], |_, _| ());
+			assert_eq!(cleaned.freed(), 6144);
+			assert!(cleaned.is_complete());
+		});
+	}
+}
@@ -0,0 +1,74 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+
+/// User-wide settings, loaded from `~/.config/swp/config.toml`
+///
+/// All values are optional. Settings passed on the command line take
+/// precedence over the values in this file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+	/// Only sweep projects that haven't been modified for at least this long
+	pub older_than: Option<String>,
+}
+
+impl Config {
+	/// Loads the user config file, if it exists
+	///
+	/// # Returns
+	/// The parsed config, or the default config if the file doesn't exist
+	pub fn load() -> Result<Config, String> {
+		let path = match config_dir() {
+			Some(dir) => dir.join("config.toml"),
+			None => return Ok(Config::default()),
+		};
+
+		let contents = match fs::read_to_string(&path) {
+			Ok(contents) => contents,
+			Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+			Err(e) => return Err(format!("{}: {}", path.to_str().unwrap_or(""), e)),
+		};
+
+		toml::from_str(&contents).map_err(|e| format!("{}: {}", path.to_str().unwrap_or(""), e))
+	}
+}
+
+/// Gets the directory containing the Sweep configuration files
+///
+/// This is `$XDG_CONFIG_HOME/swp` or `~/.config/swp` on Unix systems, and
+/// `%APPDATA%\swp` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+	if cfg!(windows) {
+		return env::var_os("APPDATA").map(|dir| PathBuf::from(dir).join("swp"));
+	}
+
+	if let Some(dir) = env::var_os("XDG_CONFIG_HOME").filter(|dir| !dir.is_empty()) {
+		return Some(PathBuf::from(dir).join("swp"));
+	}
+
+	env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("swp"))
+}
+
+#[cfg(test)]
+mod test {
+	use super::Config;
+
+	#[test]
+	fn parse() {
+		let config: Config =
+			toml::from_str("older-than = \"90d\"").expect("Could not parse config");
+		assert_eq!(config.older_than, Some("90d".to_owned()));
+
+		let config: Config = toml::from_str("").expect("Could not parse config");
+		assert_eq!(config.older_than, None);
+	}
+
+	#[test]
+	fn unknown_key() {
+		assert!(toml::from_str::<Config>("foo = 1").is_err());
+	}
+}
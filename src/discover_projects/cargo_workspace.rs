@@ -0,0 +1,304 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use toml::Value;
+
+use crate::utils::glob::compile_anchored_glob;
+
+/// The environment variables that affect where Cargo writes build output
+#[derive(Debug, Clone, Default)]
+pub struct CargoEnv {
+	/// The `CARGO_TARGET_DIR` environment variable, if it's set
+	pub target_dir: Option<PathBuf>,
+
+	/// Cargo's home directory, `CARGO_HOME` or `~/.cargo` by default
+	pub home: Option<PathBuf>,
+}
+
+impl CargoEnv {
+	/// Reads the Cargo environment of the current process
+	pub fn from_env() -> CargoEnv {
+		let target_dir = env::var_os("CARGO_TARGET_DIR")
+			.filter(|dir| !dir.is_empty())
+			.map(PathBuf::from);
+
+		let home = env::var_os("CARGO_HOME")
+			.map(PathBuf::from)
+			.or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo")));
+
+		return CargoEnv { target_dir, home };
+	}
+}
+
+/// Finds the root of the Cargo workspace a package belongs to
+///
+/// # Arguments
+/// `manifest_dir` - The directory containing the package's `Cargo.toml`
+///
+/// # Returns
+/// The directory of the workspace's `Cargo.toml`, which is `manifest_dir`
+/// itself if the package is not part of a larger workspace
+pub fn find_workspace_root(manifest_dir: &Path) -> PathBuf {
+	let manifest = read_toml(&manifest_dir.join("Cargo.toml"));
+
+	if manifest.as_ref().and_then(|m| m.get("workspace")).is_some() {
+		return manifest_dir.to_owned();
+	}
+
+	// A package can point to its workspace explicitly
+	let explicit_root = manifest
+		.as_ref()
+		.and_then(|m| m.get("package"))
+		.and_then(|p| p.get("workspace"))
+		.and_then(|w| w.as_str());
+	if let Some(root) = explicit_root {
+		let root = manifest_dir.join(root);
+		return dunce::canonicalize(&root).unwrap_or(root);
+	}
+
+	// Otherwise, Cargo uses the first parent directory with a workspace
+	// manifest, if that workspace lists the package as a member
+	for dir in manifest_dir.ancestors().skip(1) {
+		let workspace =
+			match read_toml(&dir.join("Cargo.toml")).and_then(|m| m.get("workspace").cloned()) {
+				Some(workspace) => workspace,
+				None => continue,
+			};
+
+		let relative = match manifest_dir.strip_prefix(dir) {
+			Ok(relative) => relative,
+			Err(_) => break,
+		};
+
+		if is_workspace_member(&workspace, relative) {
+			return dir.to_owned();
+		}
+		break;
+	}
+
+	return manifest_dir.to_owned();
+}
+
+/// Finds the directory a Cargo workspace writes its build output to
+///
+/// This follows the same order as Cargo: the `CARGO_TARGET_DIR` environment
+/// variable, then `build.target-dir` in the closest `.cargo/config.toml`,
+/// then `$CARGO_HOME/config.toml`, and finally `target` in the workspace
+/// root.
+///
+/// # Arguments
+/// `manifest_dir`   - The directory Cargo would be run from
+/// `workspace_root` - The root of the workspace, see `find_workspace_root`
+/// `cargo`          - The Cargo environment variables
+pub fn find_target_dir(manifest_dir: &Path, workspace_root: &Path, cargo: &CargoEnv) -> PathBuf {
+	if let Some(dir) = &cargo.target_dir {
+		// A relative path is relative to where Cargo is run from, which we
+		// don't know, so the manifest directory is the best guess
+		return manifest_dir.join(dir);
+	}
+
+	return configured_target_dir(manifest_dir, cargo.home.as_deref())
+		.unwrap_or_else(|| workspace_root.join("target"));
+}
+
+/// Finds the `build.target-dir` setting in the Cargo config files that apply
+/// to a directory
+///
+/// # Arguments
+/// `dir`        - The directory Cargo would be run from
+/// `cargo_home` - Cargo's home directory, usually `~/.cargo`
+fn configured_target_dir(dir: &Path, cargo_home: Option<&Path>) -> Option<PathBuf> {
+	let config_dirs = dir
+		.ancestors()
+		.map(|dir| dir.join(".cargo"))
+		.chain(cargo_home.map(Path::to_owned));
+
+	for config_dir in config_dirs {
+		// Cargo prefers the file without an extension if both exist
+		let config = ["config", "config.toml"]
+			.iter()
+			.map(|name| config_dir.join(name))
+			.find(|path| path.is_file())
+			.and_then(|path| read_toml(&path));
+
+		let target_dir = config
+			.as_ref()
+			.and_then(|c| c.get("build"))
+			.and_then(|b| b.get("target-dir"))
+			.and_then(|t| t.as_str());
+
+		if let Some(target_dir) = target_dir {
+			// Relative paths are relative to the directory containing `.cargo`
+			let base = config_dir.parent().unwrap_or(&config_dir);
+			return Some(base.join(target_dir));
+		}
+	}
+
+	return None;
+}
+
+/// Checks if a workspace lists a package as a member
+///
+/// # Arguments
+/// `workspace` - The `[workspace]` table of the workspace manifest
+/// `relative`  - Path of the package relative to the workspace root
+fn is_workspace_member(workspace: &Value, relative: &Path) -> bool {
+	let patterns = |key: &str| -> Vec<String> {
+		workspace
+			.get(key)
+			.and_then(|v| v.as_array())
+			.map(|array| {
+				array
+					.iter()
+					.filter_map(|v| v.as_str())
+					.map(|s| s.trim_start_matches("./").to_owned())
+					.collect()
+			})
+			.unwrap_or_default()
+	};
+
+	let excluded = patterns("exclude")
+		.iter()
+		.any(|exclude| relative.starts_with(exclude));
+	if excluded {
+		return false;
+	}
+
+	return patterns("members").iter().any(|member| {
+		compile_anchored_glob(member)
+			.map(|glob| glob.is_match(relative))
+			.unwrap_or(false)
+	});
+}
+
+fn read_toml(path: &Path) -> Option<Value> {
+	let contents = fs::read_to_string(path).ok()?;
+	return toml::from_str(&contents).ok();
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::utils::test_utils;
+
+	fn write(dir: &Path, path: &str, contents: &str) {
+		fs::write(dir.join(path), contents).expect("Could not write test file");
+	}
+
+	#[test]
+	fn workspace_root() {
+		test_utils::with_temp_dir(|dir| {
+			write(
+				dir,
+				"Cargo.toml",
+				"[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/old\"]",
+			);
+			for name in ["crates", "crates/a", "crates/old", "tools"].iter() {
+				test_utils::create_dir(dir, name);
+			}
+			write(dir, "crates/a/Cargo.toml", "[package]\nname = \"a\"");
+			write(dir, "crates/old/Cargo.toml", "[package]\nname = \"old\"");
+			write(dir, "tools/Cargo.toml", "[package]\nname = \"tools\"");
+
+			assert_eq!(find_workspace_root(dir), dir);
+			assert_eq!(find_workspace_root(&dir.join("crates/a")), dir);
+			assert_eq!(
+				find_workspace_root(&dir.join("crates/old")),
+				dir.join("crates/old")
+			);
+			assert_eq!(find_workspace_root(&dir.join("tools")), dir.join("tools"));
+		});
+	}
+
+	#[test]
+	fn explicit_workspace() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "member");
+			write(
+				dir,
+				"member/Cargo.toml",
+				"[package]\nname = \"member\"\nworkspace = \"..\"",
+			);
+
+			assert_eq!(
+				find_workspace_root(&dir.join("member")),
+				dunce::canonicalize(dir).expect("Could not canonicalize path")
+			);
+		});
+	}
+
+	#[test]
+	fn config_target_dir() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, ".cargo");
+			test_utils::create_dir(dir, "project");
+			test_utils::create_dir(dir, "other");
+			test_utils::create_dir(dir, "other/.cargo");
+			write(
+				dir,
+				".cargo/config.toml",
+				"[build]\ntarget-dir = \"shared\"",
+			);
+			write(
+				dir,
+				"other/.cargo/config",
+				"[build]\ntarget-dir = \"/tmp/other\"",
+			);
+			write(dir, "other/.cargo/config.toml", "[build]\njobs = 2");
+
+			assert_eq!(
+				configured_target_dir(&dir.join("project"), None),
+				Some(dir.join("shared"))
+			);
+			assert_eq!(
+				configured_target_dir(&dir.join("other"), None),
+				Some(PathBuf::from("/tmp/other"))
+			);
+		});
+	}
+
+	#[test]
+	fn env_target_dir() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "home");
+			test_utils::create_dir(dir, "project");
+			write(
+				dir,
+				"home/config.toml",
+				"[build]\ntarget-dir = \"/tmp/home\"",
+			);
+
+			let project = dir.join("project");
+			let mut cargo = CargoEnv::default();
+			assert_eq!(find_target_dir(&project, dir, &cargo), dir.join("target"));
+
+			cargo.home = Some(dir.join("home"));
+			assert_eq!(
+				find_target_dir(&project, dir, &cargo),
+				PathBuf::from("/tmp/home")
+			);
+
+			cargo.target_dir = Some(PathBuf::from("build"));
+			assert_eq!(
+				find_target_dir(&project, dir, &cargo),
+				project.join("build")
+			);
+		});
+	}
+
+	#[test]
+	fn cargo_home_target_dir() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "home");
+			test_utils::create_dir(dir, "project");
+			write(dir, "home/config.toml", "[build]\ntarget-dir = \"target\"");
+
+			assert_eq!(configured_target_dir(&dir.join("project"), None), None);
+			assert_eq!(
+				configured_target_dir(&dir.join("project"), Some(&dir.join("home"))),
+				Some(dir.join("target"))
+			);
+		});
+	}
+}
@@ -1,76 +1,224 @@
-use std::path::Path;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Component, Path};
 
+use crate::error::{Error, Result};
+use crate::project::Reason;
+use crate::rules::Handler;
+
+use super::cargo_workspace::{find_target_dir, find_workspace_root};
+use super::dotnet_solution::parse_solution_projects;
+use super::gradle_settings::parse_gradle_includes;
 use crate::utils::file_utils::exists_in_path;
-use crate::Project;
+use crate::utils::DirListing;
+use crate::{Project, Scanner};
 
 /// Checks if a given directory is cleanable and identifies the
 /// dependency subdirectories
 ///
 /// # Arguments
-/// `path` - The path to check
+/// `path`    - The path to check
+/// `scanner` - Configures the rules to detect projects with
 ///
 /// # Returns
-/// The identified project, or None if the given path is not a project
-pub fn detect_cleanable_project(path: &Path) -> Option<Project> {
-	// A project can only be a directory
-	if !path.is_dir() {
-		return None;
-	}
+/// The identified project, None if the given path is not a project, or an
+/// error if the directory can't be read or the project's `.swpfile` or one
+/// of the rules is invalid
+pub fn detect_cleanable_project(path: &Path, scanner: &Scanner) -> Result<Option<Project>> {
+	// A project can only be a directory. All marker files are checked
+	// against a single listing of the directory, which is a lot faster than
+	// checking each of them separately on slow or network filesystems.
+	let listing = match DirListing::read(path) {
+		Ok(listing) => listing,
+		Err(ref e) if e.kind() == ErrorKind::NotFound || !path.is_dir() => return Ok(None),
+		// The directory may be a project, so it's reported instead of
+		// silently skipped, e.g. when it can't be read by the current user
+		Err(e) => return Err(Error::io(path, e)),
+	};
 
 	// Create an empty project so we can add cleanable directories to it
-	let mut project = Project::new(path.clone());
+	let mut project = Project::new(path);
 
 	// This flag will keep track of whether we've found a project
 	let mut is_project = false;
 
 	for filename in [".swpfile", ".cleanuprc"].iter() {
-		if exists_in_path(path, filename) {
-			project.load_swpfile(filename);
+		if listing.contains(filename) {
+			// If a .swpfile file is found, it overrides the default paths so we
+			// can return early, unless it asks to include the defaults
+			if !project.load_swpfile(filename)? {
+				return Ok(Some(project));
+			}
 
-			// If a .swpfile file is found, it overrides the default paths so we can return early
-			return Some(project);
+			is_project = true;
+			break;
 		}
 	}
 
-	// Rust projects
-	if exists_in_path(path, "Cargo.toml") {
-		is_project = true;
-		project.add_cleanable_dir_if_exists("target");
-	}
+	for rule in &scanner.rules {
+		let marker = match rule.markers.iter().find_map(|m| listing.find(m)) {
+			Some(marker) => marker,
+			None => continue,
+		};
 
-	// Node.js projects
-	if exists_in_path(path, "package.json") {
-		is_project = true;
-		project.add_cleanable_dir_if_exists("node_modules");
-		project.add_cleanable_dir_if_exists(".cache");
-		project.add_cleanable_dir_if_exists("build");
-		project.add_cleanable_dir_if_exists("dist");
-		project.add_cleanable_dir_if_exists("android/app/build");
-		project.add_cleanable_dir_if_exists("android/build");
-		project.add_cleanable_dir_if_exists("android/.gradle");
-		project.add_cleanable_dir_if_exists("ios/build");
-		project.add_cleanable_dir_if_exists("ios/Pods");
-	}
-
-	// Java projects
-	if exists_in_path(path, "pom.xml") {
 		is_project = true;
-		project.add_cleanable_dir_if_exists("target");
-		project.add_cleanable_dir_if_exists(".gradle");
-		project.add_cleanable_dir_if_exists("build");
+		project.add_ecosystem(&rule.name);
+		let reason = Reason::marker(marker);
+
+		for dir in &rule.dirs {
+			// Like in a `.swpfile`, a leading `/` refers to the project root
+			if let Some(required) = rule.contains.get(dir) {
+				if !exists_in_path(&path.join(dir.trim_start_matches('/')), required) {
+					continue;
+				}
+			}
+
+			project
+				.add_cleanable_glob(dir, &reason)
+				.map_err(Error::InvalidPattern)?;
+		}
+
+		if let Some(handler) = rule.handler {
+			apply_handler(handler, &mut project, &listing, marker, &reason, scanner);
+		}
 	}
 
 	if is_project {
-		return Some(project);
+		return Ok(Some(project));
 	} else {
-		return None;
+		return Ok(None);
 	}
 }
 
+/// Adds the cleanable directories found by a rule's built-in handler
+///
+/// # Arguments
+/// `handler` - The handler to apply
+/// `project` - The detected project
+/// `listing` - Entries in the project directory
+/// `marker`  - The marker file that matched the rule
+/// `reason`  - Reason for the cleanable directories
+/// `scanner` - Configures which directories can be cleaned
+fn apply_handler(
+	handler: Handler,
+	project: &mut Project,
+	listing: &DirListing,
+	marker: &str,
+	reason: &Reason,
+	scanner: &Scanner,
+) {
+	let path = project.root().to_owned();
+
+	match handler {
+		Handler::Cargo => {
+			// Workspace members share the target directory of the workspace, which
+			// can also be moved elsewhere entirely in the Cargo config
+			let workspace_root = find_workspace_root(&path);
+			let target_dir = find_target_dir(&path, &workspace_root, &scanner.cargo);
+
+			// A target directory outside the workspace may also be used by
+			// projects that weren't found, so it's only cleaned with `--all`
+			if target_dir.starts_with(&workspace_root) || scanner.all {
+				project.add_external_dir_if_exists(target_dir, reason);
+			}
+		}
+
+		Handler::Go => {
+			// Some modules keep the sources of their commands in `bin`,
+			// rather than only the binaries built from them
+			let bin = path.join("bin");
+			if !contains_go_files(&bin) {
+				project.add_cleanable_dir_if_exists(bin, reason);
+			}
+		}
+
+		Handler::Gradle => {
+			if !marker.starts_with("settings.gradle") {
+				return;
+			}
+
+			let contents = fs::read_to_string(path.join(marker)).unwrap_or_default();
+			for subproject in parse_gradle_includes(&contents) {
+				// Included builds outside the project root belong to another project
+				if !is_inside_project(&subproject) {
+					continue;
+				}
+
+				project.add_cleanable_dir_if_exists(subproject.join("build"), reason);
+			}
+		}
+
+		Handler::DotnetSolution => {
+			for solution in listing.find_all("*.sln") {
+				let reason = Reason::marker(solution);
+				let contents = fs::read_to_string(path.join(solution)).unwrap_or_default();
+
+				for dir in parse_solution_projects(&contents) {
+					// Projects outside of the solution directory belong to another project
+					if !is_inside_project(&dir) {
+						continue;
+					}
+
+					project.add_cleanable_dir_if_exists(dir.join("bin"), &reason);
+					project.add_cleanable_dir_if_exists(dir.join("obj"), &reason);
+				}
+			}
+		}
+	}
+}
+
+/// Checks if a directory or any of its subdirectories contains `.go` files
+///
+/// Symlinked directories are not followed. Directories that can't be read
+/// are assumed to contain Go files, so they're never cleaned by mistake.
+fn contains_go_files(dir: &Path) -> bool {
+	let entries = match dir.read_dir() {
+		Ok(entries) => entries,
+		Err(ref e) if e.kind() == ErrorKind::NotFound => return false,
+		Err(_) => return true,
+	};
+
+	for entry in entries.filter_map(|e| e.ok()) {
+		let path = entry.path();
+		match entry.file_type() {
+			Ok(t) if t.is_dir() => {
+				if contains_go_files(&path) {
+					return true;
+				}
+			}
+			_ => {
+				if path.extension().map(|e| e == "go").unwrap_or(false) {
+					return true;
+				}
+			}
+		}
+	}
+
+	return false;
+}
+
+/// Checks if a path read from a project file stays inside the project, so
+/// it's relative and doesn't go up with `..`
+fn is_inside_project(path: &Path) -> bool {
+	return path.is_relative()
+		&& path
+			.components()
+			.all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+}
+
 #[cfg(test)]
 mod test {
 	use super::detect_cleanable_project;
+	use crate::discover_projects::CargoEnv;
+	use crate::project::Reason;
 	use crate::utils::test_utils;
+	use crate::Scanner;
+
+	/// Creates a scanner with the built-in rules, which ignores the Cargo
+	/// environment variables of the machine running the tests
+	fn scanner() -> Scanner {
+		return Scanner::new().cargo_env(CargoEnv::default());
+	}
 
 	/// Creates the provided files and directories in a temporary directory,
 	/// then runs `detect_cleanable_project` on that directory and verifies
@@ -100,13 +248,15 @@ mod test {
 				$(test_utils::create_dir(dir, $d);)*
 				$(test_utils::create_file(dir, $f);)*
 
-				let project = detect_cleanable_project(&dir).expect("No project detected");
+				let project = detect_cleanable_project(dir, &scanner())
+					.unwrap()
+					.expect("No project detected");
 				$(assert!(project.is_cleanable_dir(&dir.join($c)));)*
 
-				assert_eq!(project.into_cleanable_dirs().len(), {
+				assert_eq!(project.cleanable_dirs().len(), {
 					#[allow(unused_mut)]
 					let mut i = 0;
-					$(i += 1; $c;)*
+					$(i += 1; let _ = $c;)*
 					i
 				});
 			});
@@ -127,6 +277,71 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn rust_workspace() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(dir.join("Cargo.toml"), "[workspace]\nmembers = [\"app\"]")
+				.expect("Could not write test file");
+			test_utils::create_dir(dir, "target");
+			test_utils::create_dir(dir, "app");
+			test_utils::create_file(dir, "app/Cargo.toml");
+
+			let project = detect_cleanable_project(&dir.join("app"), &scanner())
+				.unwrap()
+				.expect("No project detected");
+			assert!(project.is_cleanable_dir(dir.join("target")));
+			assert_eq!(project.cleanable_dirs().len(), 1);
+		});
+	}
+
+	#[test]
+	fn rust_shared_target_dir() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "app");
+			test_utils::create_dir(dir, "shared");
+			test_utils::create_file(dir, "app/Cargo.toml");
+
+			let cargo = CargoEnv {
+				target_dir: Some(dir.join("shared")),
+				home: None,
+			};
+
+			// Other projects may use the same directory, so it's only cleaned
+			// when cleaning everything
+			let scanner = scanner().cargo_env(cargo);
+			let project = detect_cleanable_project(&dir.join("app"), &scanner)
+				.unwrap()
+				.expect("No project detected");
+			assert_eq!(project.cleanable_dirs().len(), 0);
+
+			let project = detect_cleanable_project(&dir.join("app"), &scanner.all(true))
+				.unwrap()
+				.expect("No project detected");
+			assert!(project.is_cleanable_dir(dir.join("shared")));
+		});
+	}
+
+	#[test]
+	fn go() {
+		test_project!(
+			files: ["go.mod"],
+			dirs: ["cmd", "vendor", "bin"],
+			cleanable: ["bin"]
+		);
+
+		test_project!(
+			files: ["go.mod", "vendor/modules.txt"],
+			dirs: ["cmd", "vendor"],
+			cleanable: ["vendor"]
+		);
+
+		// A `bin` directory with sources isn't build output
+		test_project!(
+			files: ["go.mod", "bin/tool/main.go"],
+			dirs: ["bin", "bin/tool"]
+		);
+	}
+
 	#[test]
 	fn nodejs() {
 		test_project!(
@@ -161,11 +376,377 @@ mod test {
 		);
 	}
 
+	#[test]
+	fn gradle() {
+		test_project!(
+			files: ["build.gradle.kts"],
+			dirs: ["src"]
+		);
+
+		test_project!(
+			files: ["build.gradle"],
+			dirs: ["src", ".gradle", "build"],
+			cleanable: [".gradle", "build"]
+		);
+
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(
+				dir.join("settings.gradle.kts"),
+				"include(\":app\", \":lib:core\")",
+			)
+			.expect("Could not write test file");
+			test_utils::create_dir(dir, ".gradle");
+			test_utils::create_dir(dir, "app");
+			test_utils::create_dir(dir, "app/build");
+			test_utils::create_dir(dir, "lib");
+			test_utils::create_dir(dir, "lib/core");
+			test_utils::create_dir(dir, "lib/core/build");
+			test_utils::create_dir(dir, "lib/build");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+
+			assert!(project.is_cleanable_dir(dir.join(".gradle")));
+			assert!(project.is_cleanable_dir(dir.join("app/build")));
+			assert!(project.is_cleanable_dir(dir.join("lib/core/build")));
+			assert_eq!(project.cleanable_dirs().len(), 3);
+			assert_eq!(project.ecosystems(), ["gradle"]);
+		});
+	}
+
+	#[test]
+	fn dotnet() {
+		test_project!(
+			files: ["App.csproj"],
+			dirs: ["Properties"]
+		);
+
+		test_project!(
+			files: ["App.fsproj"],
+			dirs: ["bin", "obj", ".vs", "packages", "Properties"],
+			cleanable: ["bin", "obj", ".vs", "packages"]
+		);
+
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(
+				dir.join("Service.sln"),
+				"Project(\"{FAE04EC0}\") = \"Api\", \"src\\Api\\Api.csproj\", \"{9A19103F}\"\nEndProject\n\
+				 Project(\"{FAE04EC0}\") = \"Shared\", \"..\\Shared\\Shared.csproj\", \"{0E8F3F1C}\"\nEndProject",
+			)
+			.expect("Could not write test file");
+			test_utils::create_dir(dir, "src");
+			test_utils::create_dir(dir, "src/Api");
+			test_utils::create_dir(dir, "src/Api/bin");
+			test_utils::create_dir(dir, "src/Api/obj");
+			test_utils::create_dir(dir, "src/Api/Controllers");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+
+			assert!(project.is_cleanable_dir(dir.join("src/Api/bin")));
+			assert!(project.is_cleanable_dir(dir.join("src/Api/obj")));
+			assert_eq!(project.cleanable_dirs().len(), 2);
+			assert_eq!(project.ecosystems(), ["dotnet"]);
+		});
+	}
+
+	#[test]
+	fn paths_outside_project() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "project");
+			test_utils::create_dir(dir, "other");
+			test_utils::create_dir(dir, "other/bin");
+			test_utils::create_dir(dir, "other/build");
+
+			let other = dir.join("other").display().to_string();
+			std::fs::write(
+				dir.join("project/settings.gradle"),
+				format!("include '../other', '{}'", other),
+			)
+			.expect("Could not write test file");
+			std::fs::write(
+				dir.join("project/App.sln"),
+				format!(
+					"Project(\"{{FAE04EC0}}\") = \"Other\", \"{}/Other.csproj\", \"{{9A19103F}}\"\nEndProject",
+					other
+				),
+			)
+			.expect("Could not write test file");
+
+			let project = detect_cleanable_project(&dir.join("project"), &scanner())
+				.unwrap()
+				.expect("No project detected");
+			assert_eq!(project.cleanable_dirs().len(), 0);
+		});
+	}
+
+	#[test]
+	fn not_a_directory() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_file(dir, "Cargo.toml");
+
+			assert!(
+				detect_cleanable_project(&dir.join("Cargo.toml"), &scanner())
+					.unwrap()
+					.is_none()
+			);
+			assert!(detect_cleanable_project(&dir.join("missing"), &scanner())
+				.unwrap()
+				.is_none());
+		});
+	}
+
+	#[test]
+	fn python() {
+		test_project!(
+			files: ["requirements.txt"],
+			dirs: ["src"]
+		);
+
+		test_project!(
+			files: ["pyproject.toml", ".venv/pyvenv.cfg"],
+			dirs: [".venv", "env", ".tox", ".pytest_cache", "dist", "mypkg.egg-info", "src", "src/__pycache__", "src/mypkg", "src/mypkg/__pycache__"],
+			cleanable: [".venv", ".tox", ".pytest_cache", "dist", "mypkg.egg-info", "src/__pycache__", "src/mypkg/__pycache__"]
+		);
+
+		test_project!(
+			files: ["setup.py", "venv/pyvenv.cfg"],
+			dirs: ["venv", "venv/lib", "venv/lib/__pycache__", "build", "src", "src/pkg.egg-info"],
+			cleanable: ["venv", "build", "src/pkg.egg-info"]
+		);
+	}
+
+	#[test]
+	fn custom_rule() {
+		let mut rules = scanner().rules.clone();
+		rules.push(
+			toml::from_str("name = \"bazel\"\nmarkers = [\"MODULE.bazel\"]\ndirs = [\"bazel-*\"]")
+				.expect("Could not parse rule"),
+		);
+
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_file(dir, "MODULE.bazel");
+			test_utils::create_dir(dir, "bazel-bin");
+			test_utils::create_dir(dir, "bazel-out");
+			test_utils::create_dir(dir, "src");
+
+			let project = detect_cleanable_project(dir, &scanner().rules(rules))
+				.unwrap()
+				.expect("No project detected");
+			assert!(project.is_cleanable_dir(dir.join("bazel-bin")));
+			assert!(project.is_cleanable_dir(dir.join("bazel-out")));
+			assert_eq!(project.cleanable_dirs().len(), 2);
+			assert_eq!(project.ecosystems(), ["bazel"]);
+		});
+	}
+
+	#[test]
+	fn custom_rule_outside_project() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "project");
+			test_utils::create_dir(dir, "project/x");
+			test_utils::create_file(dir, "project/MODULE.bazel");
+			test_utils::create_dir(dir, "x");
+			test_utils::create_dir(dir, "other");
+
+			// Parsing the rules file rejects `..`, but projects don't rely on that
+			let mut rules = scanner().rules.clone();
+			rules.push(
+				toml::from_str(&format!(
+					"name = \"bazel\"\nmarkers = [\"MODULE.bazel\"]\ndirs = [\"/x\", \"../x\", \".\", {:?}]",
+					dir.join("other").display().to_string()
+				))
+				.expect("Could not parse rule"),
+			);
+
+			let project = detect_cleanable_project(&dir.join("project"), &scanner().rules(rules))
+				.unwrap()
+				.expect("No project detected");
+
+			// A leading slash is relative to the project root
+			assert!(project.is_cleanable_dir(dir.join("project/x")));
+			assert_eq!(project.cleanable_dirs().len(), 1);
+		});
+	}
+
+	#[test]
+	fn reasons() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_file(dir, "Cargo.toml");
+			test_utils::create_dir(dir, "target");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+			assert_eq!(
+				project.cleanable_dirs()[0].reason,
+				Reason::marker("Cargo.toml")
+			);
+		});
+
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(dir.join(".swpfile"), "# comment\nout")
+				.expect("Could not write test file");
+			test_utils::create_dir(dir, "out");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+			assert_eq!(
+				project.cleanable_dirs()[0].reason,
+				Reason::Swpfile {
+					file: ".swpfile".to_owned(),
+					line: 2
+				}
+			);
+		});
+	}
+
+	#[test]
+	fn swpfile_glob() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(
+				dir.join(".swpfile"),
+				"packages/*/node_modules\n**/__pycache__\n/build",
+			)
+			.expect("Could not write test file");
+			test_utils::create_dir(dir, "build");
+			test_utils::create_dir(dir, "packages");
+			test_utils::create_dir(dir, "packages/a");
+			test_utils::create_dir(dir, "packages/a/node_modules");
+			test_utils::create_dir(dir, "packages/b");
+			test_utils::create_dir(dir, "packages/b/node_modules");
+			test_utils::create_dir(dir, "packages/b/node_modules/__pycache__");
+			test_utils::create_dir(dir, "packages/b/src");
+			test_utils::create_dir(dir, "packages/b/src/__pycache__");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+
+			assert!(project.is_cleanable_dir(dir.join("packages/a/node_modules")));
+			assert!(project.is_cleanable_dir(dir.join("packages/b/node_modules")));
+			assert!(project.is_cleanable_dir(dir.join("packages/b/src/__pycache__")));
+			// A leading slash is relative to the project, not the filesystem
+			assert!(project.is_cleanable_dir(dir.join("build")));
+
+			// Directories inside other cleanable directories aren't listed separately
+			assert_eq!(project.cleanable_dirs().len(), 4);
+		});
+	}
+
+	#[test]
+	fn swpfile_defaults() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(dir.join(".swpfile"), "!build\n@defaults\ncoverage")
+				.expect("Could not write test file");
+			test_utils::create_file(dir, "package.json");
+			test_utils::create_dir(dir, "node_modules");
+			test_utils::create_dir(dir, "build");
+			test_utils::create_dir(dir, "coverage");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+
+			assert!(project.is_cleanable_dir(dir.join("node_modules")));
+			assert!(project.is_cleanable_dir(dir.join("coverage")));
+			assert!(!project.is_cleanable_dir(dir.join("build")));
+			assert_eq!(project.cleanable_dirs().len(), 2);
+			assert_eq!(project.ecosystems(), ["nodejs"]);
+		});
+	}
+
+	#[test]
+	fn swpfile_exclusions() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(
+				dir.join(".swpfile"),
+				"packages/*/node_modules\n!packages/keep\n**/__pycache__",
+			)
+			.expect("Could not write test file");
+			test_utils::create_dir(dir, "packages");
+			test_utils::create_dir(dir, "packages/a");
+			test_utils::create_dir(dir, "packages/a/node_modules");
+			test_utils::create_dir(dir, "packages/keep");
+			test_utils::create_dir(dir, "packages/keep/node_modules");
+			test_utils::create_dir(dir, "packages/keep/__pycache__");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+
+			assert!(project.is_cleanable_dir(dir.join("packages/a/node_modules")));
+			assert_eq!(project.cleanable_dirs().len(), 1);
+		});
+	}
+
+	#[test]
+	fn swpfile_nested_exclusions() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(
+				dir.join(".swpfile"),
+				"dist\nnode_modules\n**/cache\n!dist/assets\n!**/keep",
+			)
+			.expect("Could not write test file");
+			test_utils::create_dir(dir, "dist");
+			test_utils::create_dir(dir, "dist/assets");
+			test_utils::create_dir(dir, "node_modules");
+			test_utils::create_dir(dir, "node_modules/a");
+			test_utils::create_dir(dir, "node_modules/a/keep");
+			test_utils::create_dir(dir, "src");
+			test_utils::create_dir(dir, "src/cache");
+			test_utils::create_dir(dir, "src/cache/keep");
+			test_utils::create_dir(dir, "test");
+			test_utils::create_dir(dir, "test/cache");
+
+			let project = detect_cleanable_project(dir, &scanner())
+				.unwrap()
+				.expect("No project detected");
+
+			// Deleting these would also delete the excluded directories in them
+			assert!(!project.is_cleanable_dir(dir.join("dist")));
+			assert!(!project.is_cleanable_dir(dir.join("node_modules")));
+			assert!(!project.is_cleanable_dir(dir.join("src/cache")));
+
+			assert!(project.is_cleanable_dir(dir.join("test/cache")));
+			assert_eq!(project.cleanable_dirs().len(), 1);
+		});
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn symlinks() {
+		use std::os::unix::fs::symlink;
+
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "project");
+			test_utils::create_dir(dir, "elsewhere");
+			test_utils::create_dir(dir, "elsewhere/build");
+			test_utils::create_dir(dir, "cache");
+			test_utils::create_file(dir, "project/package.json");
+			symlink(dir.join("elsewhere"), dir.join("project/android")).unwrap();
+			symlink(dir.join("cache"), dir.join("project/.cache")).unwrap();
+
+			let project = detect_cleanable_project(&dir.join("project"), &scanner())
+				.unwrap()
+				.expect("No project detected");
+
+			// A symlinked directory can be cleaned by removing the link, but
+			// directories inside it are outside of the project
+			assert!(project.is_cleanable_dir(dir.join("project/.cache")));
+			assert!(project.cleanable_dirs()[0].is_symlink());
+			assert_eq!(project.cleanable_dirs().len(), 1);
+		});
+	}
+
 	#[test]
 	fn empty_dir() {
 		test_utils::with_temp_dir(|dir| {
 			assert!(
-				detect_cleanable_project(&dir).is_none(),
+				detect_cleanable_project(dir, &scanner()).unwrap().is_none(),
 				"Project detected in empty directory"
 			);
 		});
@@ -179,7 +760,7 @@ mod test {
 			test_utils::create_file(dir, "no_project_here.txt");
 
 			assert!(
-				detect_cleanable_project(&dir).is_none(),
+				detect_cleanable_project(dir, &scanner()).unwrap().is_none(),
 				"Project detected in unrelated directory"
 			);
 		});
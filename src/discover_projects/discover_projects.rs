@@ -1,16 +1,25 @@
 use std::cmp;
+use std::collections::HashSet;
+use std::fs::{self, DirEntry, Metadata};
 use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
 
 use crossbeam::queue::SegQueue;
-use yansi::Color;
 
-use crate::output;
+use crate::error::{Error, Result};
 use crate::utils::process_queue;
-use crate::Project;
-use crate::Settings;
+use crate::{Event, Project, Scanner};
 
 use super::detect_cleanable_project::detect_cleanable_project;
+use super::go_cache::find_go_caches;
+
+/// Identifies a directory regardless of the path it was reached through
+#[cfg(unix)]
+type DirId = (u64, u64);
+
+#[cfg(not(unix))]
+type DirId = PathBuf;
 
 /// Recursively walks the configured paths and discovers all cleanable directories
 ///
@@ -18,39 +27,72 @@ use super::detect_cleanable_project::detect_cleanable_project;
 /// will spawn several worker threads to improve processing throughput
 ///
 /// # Arguments
-/// `settings` - The application settings object
+/// `scanner` - Configures where and how to search
+///
+/// Directories that can't be searched and projects that can't be detected
+/// are reported as events and skipped.
 ///
 /// # Returns
-/// A queue containing all discovered projects
-pub fn discover_projects(settings: &Settings) -> Option<SegQueue<Project>> {
-	// Will contain a queue of paths that still need to be processed
+/// A queue containing all discovered projects, or an error if searching
+/// stopped unexpectedly
+pub fn discover_projects(scanner: &Scanner) -> Result<SegQueue<Project>> {
+	// Will contain a queue of paths that still need to be processed, along
+	// with the device they should be on when staying on one filesystem
 	let path_queue = SegQueue::new();
 
+	// Directories that have been searched, to avoid searching them again
+	// when following symlinks
+	let visited = Mutex::new(HashSet::new());
+
 	// Will contain all discovered cleanable directories
 	let discovered = SegQueue::new();
 
 	// Atomic counter for the total number of paths processed
 	// Just for displaying this information in the output, it's
 	// not used for anything else
-	let total_paths = AtomicUsize::new(settings.paths.len());
+	let total_paths = AtomicUsize::new(scanner.paths.len());
 
 	// Before starting, check if any of the configured paths are cleanable and
 	// discover the first level of subdirectories, to ensure the paths queue
 	// already contains several directories. If the paths queue has enough
 	// paths queued for all threads to start working immediately, the work
-	// will finish faster and there will be less risk of threads timing out
-	// before all paths have been processed.
-	for path in &settings.paths {
-		if let Some(project) = detect_cleanable_project(&path) {
-			discovered.push(project);
+	// will finish faster.
+	for path in &scanner.paths {
+		let metadata = match fs::metadata(path) {
+			Ok(metadata) => metadata,
+			Err(_) => continue,
+		};
+
+		let device = if scanner.one_file_system {
+			device_of(&metadata)
 		} else {
-			discover_projects_in_directory(&path, &settings, &path_queue, &discovered);
+			None
+		};
+
+		if scanner.follow_symlinks {
+			visited.lock().unwrap().insert(dir_id(path, &metadata));
+		}
+
+		match detect_cleanable_project(path, scanner) {
+			Ok(Some(project)) => found(keep_on_device(project, device), scanner, &discovered),
+			Ok(None) => discover_projects_in_directory(
+				path,
+				device,
+				scanner,
+				&visited,
+				&path_queue,
+				&discovered,
+			),
+			Err(e) => scanner.emit(Event::ProjectSkipped {
+				root: path,
+				error: &e,
+			}),
 		}
 	}
 
 	// If there was only one level to crawl, the queue will be empty after this
 	// and the thread creation can be skipped entirely
-	if path_queue.len() > 0 {
+	if !path_queue.is_empty() {
 		// I've set the number of threads to twice the number of CPU cores but
 		// this is not based on any real insights. It is an assumption of what
 		// might be a good balance between read speed, disk usage and CPU usage.
@@ -61,31 +103,40 @@ pub fn discover_projects(settings: &Settings) -> Option<SegQueue<Project>> {
 		process_queue(
 			thread_count,
 			&path_queue,
-			|path| {
-				output::print("Searching", Color::Cyan, path.to_str().unwrap_or(""));
+			&scanner.cancel,
+			|(path, device)| {
+				scanner.emit(Event::Searching(&path));
 
 				total_paths.fetch_add(1, Ordering::SeqCst);
-				discover_projects_in_directory(&path, &settings, &path_queue, &discovered);
-			},
-			|tries| {
-				output::print("Searching", Color::Cyan, &".".repeat(tries));
+				discover_projects_in_directory(
+					&path,
+					device,
+					scanner,
+					&visited,
+					&path_queue,
+					&discovered,
+				);
 			},
-		);
+		)?;
 	}
 
-	let total_paths = total_paths.into_inner();
-	let message = if total_paths == 1 {
-		format!("1 directory searched")
-	} else {
-		format!("{} directories searched", total_paths)
-	};
-	output::println("Searched", Color::Green, &message);
-
-	if discovered.len() == 0 {
-		None
-	} else {
-		Some(discovered)
+	if scanner.go_cache {
+		for project in find_go_caches() {
+			found(project, scanner, &discovered);
+		}
 	}
+
+	scanner.emit(Event::Searched {
+		directories: total_paths.into_inner(),
+	});
+
+	return Ok(discovered);
+}
+
+/// Reports a discovered project and adds it to the results
+fn found(project: Project, scanner: &Scanner, discovered: &SegQueue<Project>) {
+	scanner.emit(Event::Found(&project));
+	discovered.push(project);
 }
 
 /// Discovers the subdirectories of a given path
@@ -94,13 +145,17 @@ pub fn discover_projects(settings: &Settings) -> Option<SegQueue<Project>> {
 ///
 /// # Arguments
 /// `path`       - Path to search
-/// `settings`   - The application settings object
+/// `device`     - The device subdirectories must be on, if set
+/// `scanner`    - Configures where and how to search
+/// `visited`    - Directories that have already been searched
 /// `path_queue` - Subdirectories that need to be discovered will be added to this queue
 /// `discovered` - Identified cleanable projects will be added to this queue
 fn discover_projects_in_directory(
 	path: &Path,
-	settings: &Settings,
-	path_queue: &SegQueue<PathBuf>,
+	device: Option<u64>,
+	scanner: &Scanner,
+	visited: &Mutex<HashSet<DirId>>,
+	path_queue: &SegQueue<(PathBuf, Option<u64>)>,
 	discovered: &SegQueue<Project>,
 ) {
 	// We can only read in directories
@@ -110,24 +165,145 @@ fn discover_projects_in_directory(
 
 	let read_dir = match path.read_dir() {
 		Err(e) => {
-			output::error(e.to_string());
-			output::println_info(path.to_str().unwrap_or(""));
+			scanner.emit(Event::SearchFailed(&Error::io(path, e)));
 			return;
 		}
 
 		Ok(entries) => entries
 			.filter_map(|entry| entry.ok())
+			.filter(|entry| should_search(entry, device, scanner, visited))
 			.map(|entry| entry.path())
-			.filter(|path| path.is_dir())
-			.filter(|path| !settings.is_path_ignored(path)),
+			.filter(|path| !scanner.is_path_ignored(path)),
 	};
 
 	// Go over all subdirectories in the given directory and check if they're cleanable
 	for path in read_dir {
-		if let Some(project) = detect_cleanable_project(&path) {
-			discovered.push(project);
-		} else {
-			path_queue.push(path);
+		match detect_cleanable_project(&path, scanner) {
+			Ok(Some(project)) => found(keep_on_device(project, device), scanner, discovered),
+			Ok(None) => path_queue.push((path, device)),
+			Err(e) => scanner.emit(Event::ProjectSkipped {
+				root: &path,
+				error: &e,
+			}),
 		}
 	}
 }
+
+/// Checks if a directory entry is a directory that should be searched
+///
+/// Symlinks are only searched when following symlinks, and only if the
+/// directory they point to wasn't searched before. Otherwise a symlink to a
+/// parent directory would be searched over and over again.
+///
+/// # Arguments
+/// `entry`    - The entry to check
+/// `device`   - The device the directory must be on, if set
+/// `scanner`  - Configures where and how to search
+/// `visited`  - Directories that have already been searched
+fn should_search(
+	entry: &DirEntry,
+	device: Option<u64>,
+	scanner: &Scanner,
+	visited: &Mutex<HashSet<DirId>>,
+) -> bool {
+	// `DirEntry::file_type` does not follow symlinks
+	let file_type = match entry.file_type() {
+		Ok(file_type) => file_type,
+		Err(_) => return false,
+	};
+
+	if file_type.is_symlink() {
+		if !scanner.follow_symlinks {
+			return false;
+		}
+	} else if !file_type.is_dir() {
+		return false;
+	}
+
+	let path = entry.path();
+	let metadata = match fs::metadata(&path) {
+		Ok(metadata) if metadata.is_dir() => metadata,
+		_ => return false,
+	};
+
+	if device.is_some() && device_of(&metadata) != device {
+		return false;
+	}
+
+	if scanner.follow_symlinks {
+		return visited.lock().unwrap().insert(dir_id(&path, &metadata));
+	}
+
+	return true;
+}
+
+/// Removes the cleanable directories of a project that are on another
+/// device, when staying on one filesystem
+fn keep_on_device(mut project: Project, device: Option<u64>) -> Project {
+	if device.is_some() {
+		project.retain_cleanable_dirs(|dir| {
+			fs::symlink_metadata(&dir.path)
+				.map(|metadata| device_of(&metadata) == device)
+				.unwrap_or(false)
+		});
+	}
+
+	return project;
+}
+
+/// Gets the device a file is on
+#[cfg(unix)]
+fn device_of(metadata: &Metadata) -> Option<u64> {
+	use std::os::unix::fs::MetadataExt;
+	return Some(metadata.dev());
+}
+
+#[cfg(not(unix))]
+fn device_of(_metadata: &Metadata) -> Option<u64> {
+	return None;
+}
+
+/// Gets the identity of a directory, which is the same for all paths that
+/// lead to it
+#[cfg(unix)]
+fn dir_id(_path: &Path, metadata: &Metadata) -> DirId {
+	use std::os::unix::fs::MetadataExt;
+	return (metadata.dev(), metadata.ino());
+}
+
+#[cfg(not(unix))]
+fn dir_id(path: &Path, _metadata: &Metadata) -> DirId {
+	return dunce::canonicalize(path).unwrap_or_else(|_| path.to_owned());
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::utils::test_utils;
+
+	#[cfg(unix)]
+	#[test]
+	fn symlinks() {
+		use std::os::unix::fs::symlink;
+
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "real");
+			test_utils::create_dir(dir, "real/app");
+			test_utils::create_file(dir, "real/app/package.json");
+			symlink(dir.join("real"), dir.join("link")).unwrap();
+			symlink(dir, dir.join("real/loop")).unwrap();
+
+			let scanner = Scanner::new().path(dir).one_file_system(true);
+
+			let discovered = discover_projects(&scanner).unwrap();
+			assert_eq!(discovered.pop().unwrap().root(), dir.join("real/app"));
+			assert!(discovered.is_empty());
+
+			// The same project is reachable through the link and the loop,
+			// but it's only discovered once
+			let scanner = scanner.follow_symlinks(true);
+			let discovered = discover_projects(&scanner).unwrap();
+			assert_eq!(discovered.len(), 1);
+		});
+	}
+}
@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+
+/// Extensions of .NET project files
+const DOTNET_PROJECT_EXTENSIONS: [&str; 3] = ["csproj", "fsproj", "vbproj"];
+
+/// Finds the projects listed in a Visual Studio solution file
+///
+/// Projects are listed on lines like
+/// `Project("{FAE04EC0-...}") = "App", "src\App\App.csproj", "{9A19103F-...}"`.
+/// Solution folders are listed the same way, but don't point to a project
+/// file so they're skipped.
+///
+/// # Arguments
+/// `contents` - Contents of a `.sln` file
+///
+/// # Returns
+/// The directories of the listed projects, relative to the solution
+pub fn parse_solution_projects(contents: &str) -> Vec<PathBuf> {
+	let mut projects = Vec::new();
+
+	for line in contents.lines() {
+		let line = line.trim();
+		if !line.starts_with("Project(") {
+			continue;
+		}
+
+		let value = match line.split_once('=') {
+			Some((_, value)) => value,
+			None => continue,
+		};
+
+		// The path is the second quoted value after the `=`
+		let path = match value.split('"').nth(3) {
+			Some(path) => path.replace('\\', "/"),
+			None => continue,
+		};
+
+		let path = PathBuf::from(path);
+		let is_project = path
+			.extension()
+			.and_then(|e| e.to_str())
+			.map(|e| DOTNET_PROJECT_EXTENSIONS.contains(&e))
+			.unwrap_or(false);
+		if !is_project {
+			continue;
+		}
+
+		let dir = path.parent().map(|p| p.to_owned()).unwrap_or_default();
+		if !projects.contains(&dir) {
+			projects.push(dir);
+		}
+	}
+
+	return projects;
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn solution() {
+		const SOLUTION: &str = r#"
+Microsoft Visual Studio Solution File, Format Version 12.00
+Project("{FAE04EC0-301F-11D3-BF4B-00C04F79EFBC}") = "Api", "src\Api\Api.csproj", "{9A19103F-16F7-4668-BE54-9A1E7A4F7556}"
+EndProject
+Project("{2150E333-8FDC-42A3-9474-1A3956D46DE8}") = "tests", "tests", "{5B6F9E1A-2D1C-4E7A-9C2B-1F0E3D4C5B6A}"
+EndProject
+Project("{F2A71F9B-5D33-465A-A702-920D77279786}") = "Core", "src\Core\Core.fsproj", "{0E8F3F1C-3D1B-4B8A-8E2F-6A7C9B0D1E2F}"
+EndProject
+Project("{FAE04EC0-301F-11D3-BF4B-00C04F79EFBC}") = "Root", "Root.csproj", "{1A2B3C4D-5E6F-7A8B-9C0D-1E2F3A4B5C6D}"
+EndProject
+"#;
+
+		assert_eq!(
+			parse_solution_projects(SOLUTION),
+			vec![
+				PathBuf::from("src/Api"),
+				PathBuf::from("src/Core"),
+				PathBuf::from("")
+			]
+		);
+	}
+}
@@ -0,0 +1,182 @@
+use std::env;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::project::Reason;
+use crate::Project;
+
+/// Finds the global Go build and module caches
+///
+/// Each cache is returned as a separate project so it's listed on its own.
+/// The caches can be restored by Go at any time, so they are always swept
+/// regardless of when they were last used.
+///
+/// # Returns
+/// A project for each cache that exists
+pub fn find_go_caches() -> Vec<Project> {
+	let reason = Reason::Flag("--go-cache".to_owned());
+
+	let dirs = go_env().unwrap_or_else(|| default_cache_dirs(|var| env::var_os(var)));
+
+	let mut projects = Vec::new();
+	for dir in dirs {
+		let mut project = Project::new(dir.clone());
+		project.add_ecosystem("go");
+		project.set_ignore_age(true);
+		project.add_external_dir_if_exists(dir, &reason);
+
+		if !project.cleanable_dirs().is_empty() {
+			projects.push(project);
+		}
+	}
+
+	return projects;
+}
+
+/// Asks the `go` command where the caches are, which also takes settings
+/// made with `go env -w` into account
+///
+/// # Returns
+/// The cache directories, or None if Go isn't installed
+fn go_env() -> Option<Vec<PathBuf>> {
+	let output = Command::new("go")
+		.args(["env", "GOCACHE", "GOMODCACHE"])
+		.output()
+		.ok()?;
+
+	if !output.status.success() {
+		return None;
+	}
+
+	let stdout = String::from_utf8(output.stdout).ok()?;
+	return Some(
+		stdout
+			.lines()
+			.map(|line| line.trim())
+			.filter(|line| !line.is_empty() && *line != "off")
+			.map(PathBuf::from)
+			.collect(),
+	);
+}
+
+/// Determines the default cache directories the way Go does, for when the
+/// `go` command isn't available
+///
+/// # Arguments
+/// `var` - Gets the value of an environment variable
+///
+/// # Returns
+/// The build cache and module cache directories, if they could be determined
+fn default_cache_dirs<F: Fn(&str) -> Option<OsString>>(var: F) -> Vec<PathBuf> {
+	let var = |name: &str| var(name).filter(|value| !value.is_empty());
+	let home = var(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from);
+
+	let mut dirs = Vec::new();
+
+	let build_cache = match var("GOCACHE") {
+		Some(dir) if dir == "off" => None,
+		Some(dir) => Some(PathBuf::from(dir)),
+		None if cfg!(windows) => var("LocalAppData").map(|dir| PathBuf::from(dir).join("go-build")),
+		None if cfg!(target_os = "macos") => home
+			.as_ref()
+			.map(|home| home.join("Library").join("Caches").join("go-build")),
+		None => var("XDG_CACHE_HOME")
+			.map(PathBuf::from)
+			.or_else(|| home.as_ref().map(|home| home.join(".cache")))
+			.map(|dir| dir.join("go-build")),
+	};
+	dirs.extend(build_cache);
+
+	// The module cache is in the first directory of GOPATH
+	let module_cache = match var("GOMODCACHE") {
+		Some(dir) => Some(PathBuf::from(dir)),
+		None => var("GOPATH")
+			.and_then(|paths| env::split_paths(&paths).next())
+			.or_else(|| home.map(|home| home.join("go")))
+			.map(|gopath| gopath.join("pkg").join("mod")),
+	};
+	dirs.extend(module_cache);
+
+	return dirs;
+}
+
+/// Finds the directories Go may keep its module cache in, which contain the
+/// read-only sources of every downloaded module, each with its own `go.mod`
+///
+/// # Arguments
+/// `var` - Gets the value of an environment variable
+///
+/// # Returns
+/// `GOMODCACHE` if it's set, and `pkg/mod` in every GOPATH directory
+pub fn module_cache_dirs<F: Fn(&str) -> Option<OsString>>(var: F) -> Vec<PathBuf> {
+	let var = |name: &str| var(name).filter(|value| !value.is_empty());
+	let home = var(if cfg!(windows) { "USERPROFILE" } else { "HOME" }).map(PathBuf::from);
+
+	let mut dirs: Vec<PathBuf> = var("GOMODCACHE").map(PathBuf::from).into_iter().collect();
+
+	let gopaths: Vec<PathBuf> = match var("GOPATH") {
+		Some(paths) => env::split_paths(&paths).collect(),
+		None => home.map(|home| home.join("go")).into_iter().collect(),
+	};
+	dirs.extend(gopaths.iter().map(|gopath| gopath.join("pkg").join("mod")));
+
+	return dirs;
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::collections::HashMap;
+
+	#[test]
+	fn default_dirs() {
+		let vars: HashMap<&str, &str> = [("GOCACHE", "/cache/go"), ("GOMODCACHE", "/cache/mod")]
+			.iter()
+			.cloned()
+			.collect();
+		assert_eq!(
+			default_cache_dirs(|name| vars.get(name).map(OsString::from)),
+			vec![PathBuf::from("/cache/go"), PathBuf::from("/cache/mod")]
+		);
+
+		let vars: HashMap<&str, &str> = [("GOCACHE", "off"), ("GOPATH", "/go")]
+			.iter()
+			.cloned()
+			.collect();
+		assert_eq!(
+			default_cache_dirs(|name| vars.get(name).map(OsString::from)),
+			vec![PathBuf::from("/go/pkg/mod")]
+		);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn module_caches() {
+		let vars: HashMap<&str, &str> = [("GOMODCACHE", "/cache/mod"), ("GOPATH", "/go:/work/go")]
+			.iter()
+			.cloned()
+			.collect();
+		assert_eq!(
+			module_cache_dirs(|name| vars.get(name).map(OsString::from)),
+			vec![
+				PathBuf::from("/cache/mod"),
+				PathBuf::from("/go/pkg/mod"),
+				PathBuf::from("/work/go/pkg/mod")
+			]
+		);
+	}
+
+	#[cfg(target_os = "linux")]
+	#[test]
+	fn default_dirs_linux() {
+		let vars: HashMap<&str, &str> = [("HOME", "/home/me")].iter().cloned().collect();
+		assert_eq!(
+			default_cache_dirs(|name| vars.get(name).map(OsString::from)),
+			vec![
+				PathBuf::from("/home/me/.cache/go-build"),
+				PathBuf::from("/home/me/go/pkg/mod")
+			]
+		);
+	}
+}
@@ -0,0 +1,159 @@
+use std::path::PathBuf;
+
+/// Finds the subprojects included in a Gradle settings file
+///
+/// This understands the common forms of `include` in both the Groovy and
+/// Kotlin DSL, e.g. `include 'app', ':lib:core'` or `include(":app")`.
+/// Subprojects are assumed to be in their default location, so a custom
+/// `projectDir` is not taken into account.
+///
+/// # Arguments
+/// `contents` - Contents of a `settings.gradle` or `settings.gradle.kts` file
+///
+/// # Returns
+/// The directories of the included subprojects, relative to the project root
+pub fn parse_gradle_includes(contents: &str) -> Vec<PathBuf> {
+	let source = strip_comments(contents);
+	let mut subprojects = Vec::new();
+
+	let mut rest = source.as_str();
+	while let Some(index) = rest.find("include") {
+		let before = rest[..index]
+			.chars()
+			.rev()
+			.find(|c| *c != ' ' && *c != '\t');
+		let after = &rest[index + "include".len()..];
+		rest = after;
+
+		// Only look at `include` calls at the start of a statement, and skip
+		// identifiers that contain the word, like `includeBuild`
+		if !matches!(
+			before,
+			None | Some('\n') | Some(';') | Some('{') | Some('}')
+		) {
+			continue;
+		}
+		if after.chars().next().map(is_identifier_char).unwrap_or(true) {
+			continue;
+		}
+
+		// Read the string literals that follow, separated by commas, until
+		// anything else is found
+		let mut chars = after.char_indices().peekable();
+		while let Some((_, c)) = chars.next() {
+			match c {
+				c if c.is_whitespace() || c == ',' || c == '(' => continue,
+				'"' | '\'' => {
+					let name: String = chars
+						.by_ref()
+						.map(|(_, c)| c)
+						.take_while(|ch| *ch != c)
+						.collect();
+
+					let dir: PathBuf = name.split(':').filter(|part| !part.is_empty()).collect();
+					if !dir.as_os_str().is_empty() && !subprojects.contains(&dir) {
+						subprojects.push(dir);
+					}
+				}
+				_ => break,
+			}
+		}
+	}
+
+	return subprojects;
+}
+
+fn is_identifier_char(c: char) -> bool {
+	c.is_alphanumeric() || c == '_' || c == '.'
+}
+
+/// Removes `//` and `/* */` comments from a Gradle script
+fn strip_comments(contents: &str) -> String {
+	let mut stripped = String::with_capacity(contents.len());
+	let mut chars = contents.chars().peekable();
+	let mut quote = None;
+
+	while let Some(c) = chars.next() {
+		if let Some(q) = quote {
+			if c == q {
+				quote = None;
+			}
+			stripped.push(c);
+			continue;
+		}
+
+		match (c, chars.peek()) {
+			('"', _) | ('\'', _) => {
+				quote = Some(c);
+				stripped.push(c);
+			}
+			('/', Some('/')) => {
+				while chars.peek().filter(|c| **c != '\n').is_some() {
+					chars.next();
+				}
+			}
+			('/', Some('*')) => {
+				chars.next();
+				let mut previous = ' ';
+				for c in chars.by_ref() {
+					if previous == '*' && c == '/' {
+						break;
+					}
+					previous = c;
+				}
+				stripped.push(' ');
+			}
+			_ => stripped.push(c),
+		}
+	}
+
+	return stripped;
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn groovy() {
+		let contents = r"
+			rootProject.name = 'demo'
+			include 'app', ':lib:core'
+			include ':docs' // documentation
+			includeBuild 'build-logic'
+			";
+
+		assert_eq!(
+			parse_gradle_includes(contents),
+			vec![
+				PathBuf::from("app"),
+				PathBuf::from("lib/core"),
+				PathBuf::from("docs")
+			]
+		);
+	}
+
+	#[test]
+	fn kotlin() {
+		let contents = r#"
+			rootProject.name = "demo"
+			include(
+				":app",
+				":feature:login", // the login screen
+			)
+			/* include(":disabled") */
+			include("app")
+			"#;
+
+		assert_eq!(
+			parse_gradle_includes(contents),
+			vec![PathBuf::from("app"), PathBuf::from("feature/login")]
+		);
+	}
+
+	#[test]
+	fn no_includes() {
+		assert!(parse_gradle_includes("rootProject.name = 'include'").is_empty());
+		assert!(parse_gradle_includes("rootProject.name = 'include \\'app\\''").is_empty());
+	}
+}
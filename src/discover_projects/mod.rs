@@ -1,4 +1,10 @@
+mod cargo_workspace;
 mod detect_cleanable_project;
 mod discover_projects;
+mod dotnet_solution;
+mod go_cache;
+mod gradle_settings;
 
+pub use self::cargo_workspace::{find_target_dir, find_workspace_root, CargoEnv};
 pub use self::discover_projects::discover_projects;
+pub use self::go_cache::module_cache_dirs;
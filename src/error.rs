@@ -0,0 +1,96 @@
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Something that went wrong while scanning a directory or project
+#[derive(Debug)]
+pub enum Error {
+	/// A file or directory could not be read
+	Io { path: PathBuf, source: io::Error },
+
+	/// The current user isn't allowed to read a file or directory
+	PermissionDenied { path: PathBuf },
+
+	/// A file contains an invalid line, e.g. an unknown `.swpfile` directive
+	Parse {
+		path: PathBuf,
+		line: usize,
+		message: String,
+	},
+
+	/// A rule contains an invalid glob pattern
+	InvalidPattern(String),
+
+	/// A worker thread stopped unexpectedly, so the results are incomplete
+	Thread,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+	/// Creates an error for an I/O operation on a path that failed
+	///
+	/// Permission errors get their own variant, since they're usually
+	/// expected rather than a sign that something is wrong.
+	pub(crate) fn io<P: Into<PathBuf>>(path: P, source: io::Error) -> Error {
+		if source.kind() == io::ErrorKind::PermissionDenied {
+			return Error::PermissionDenied { path: path.into() };
+		}
+
+		return Error::Io {
+			path: path.into(),
+			source,
+		};
+	}
+
+	/// Gets the file or directory the error is about, if any
+	pub fn path(&self) -> Option<&Path> {
+		match self {
+			Error::Io { path, .. }
+			| Error::PermissionDenied { path }
+			| Error::Parse { path, .. } => Some(path),
+			Error::InvalidPattern(_) | Error::Thread => None,
+		}
+	}
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+			Error::PermissionDenied { path } => write!(f, "{}: Permission denied", path.display()),
+			Error::Parse {
+				path,
+				line,
+				message,
+			} => write!(f, "{} line {}: {}", path.display(), line, message),
+			Error::InvalidPattern(message) => write!(f, "Invalid pattern: {}", message),
+			Error::Thread => write!(f, "A worker thread stopped unexpectedly"),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Io { source, .. } => Some(source),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn permission_denied() {
+		let error = Error::io("target", io::Error::from(io::ErrorKind::PermissionDenied));
+		assert!(matches!(error, Error::PermissionDenied { .. }));
+		assert_eq!(error.to_string(), "target: Permission denied");
+
+		let error = Error::io("target", io::Error::from(io::ErrorKind::NotFound));
+		assert!(matches!(error, Error::Io { .. }));
+		assert_eq!(error.path(), Some(Path::new("target")));
+	}
+}
@@ -0,0 +1,77 @@
+use std::path::Path;
+
+use crate::{Error, Project};
+
+/// Progress and problems reported by a `Scanner`
+///
+/// Events can be sent from several threads at once, so they may arrive in
+/// any order within a step.
+#[derive(Debug)]
+pub enum Event<'a> {
+	/// Searching a directory for projects
+	Searching(&'a Path),
+
+	/// Found a project with cleanable directories. It hasn't been analysed
+	/// yet, so its directories may still be filtered out.
+	Found(&'a Project),
+
+	/// Finished searching for projects
+	Searched { directories: usize },
+
+	/// A directory could not be searched, so projects inside it are missed
+	SearchFailed(&'a Error),
+
+	/// A project is not cleaned at all, e.g. because its `.swpfile` is
+	/// invalid or one of its directories could not be read
+	ProjectSkipped { root: &'a Path, error: &'a Error },
+
+	/// A project is not cleaned, because it was modified more recently than
+	/// the age threshold. Its directories that are only pruned may still be
+	/// cleaned.
+	ProjectRecent(&'a Project),
+
+	/// Checking when the files in a directory were last modified
+	Analysing(&'a Path),
+
+	/// Finished checking when the projects were last modified
+	///
+	/// `cleanable` projects are old enough to be cleaned, `recent` projects
+	/// were modified too recently.
+	Analysed { cleanable: usize, recent: usize },
+
+	/// A cleanable directory is kept, even though its project can be cleaned
+	Skipped {
+		path: &'a Path,
+		reason: SkipReason<'a>,
+	},
+
+	/// Started measuring the cleanable directories
+	///
+	/// `directories` is the number of paths that will be measured, which
+	/// is more than the number of cleanable directories when pruning.
+	MeasureStarted { directories: usize },
+
+	/// Measured the files directly inside a directory, which take up
+	/// `bytes` on disk
+	Measuring { path: &'a Path, bytes: u64 },
+
+	/// Finished measuring one of the paths, including everything inside it
+	DirMeasured { path: &'a Path, size: u64 },
+
+	/// Finished measuring the cleanable directories
+	Measured { directories: usize },
+}
+
+/// Why a cleanable directory is kept
+#[derive(Debug)]
+pub enum SkipReason<'a> {
+	/// The directory contains files tracked by git
+	TrackedByGit,
+
+	/// Could not check if the directory contains files tracked by git
+	GitFailed(&'a Error),
+
+	/// The directory is also used by the project with the given root, which
+	/// was modified recently
+	UsedBy(&'a Path),
+}
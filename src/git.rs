@@ -0,0 +1,464 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::path::{Path, PathBuf};
+
+/// Size of the fixed part of an index entry before the object hash: ctime,
+/// mtime, dev, ino, mode, uid, gid and size, each stored as 32-bit values
+const ENTRY_STAT_SIZE: usize = 40;
+
+/// Flag set on index entries in version 3 and up when they have an extra
+/// 16-bit flags field
+const EXTENDED_FLAG: u16 = 0x4000;
+
+/// Signature of the index extension that links a split index to the shared
+/// index it's based on
+const LINK_EXTENSION: &[u8] = b"link";
+
+/// The files tracked by a git repository, read from its `.git/index` file
+///
+/// This reads the index directly instead of running `git ls-files`, so it
+/// works without having git installed.
+#[derive(Debug)]
+pub struct GitIndex {
+	/// The working tree root of the repository
+	root: PathBuf,
+
+	/// Paths of all tracked files relative to `root`, separated by `/` and
+	/// sorted the same way git sorts them
+	paths: Vec<String>,
+}
+
+/// Finds the git repository containing a path
+///
+/// # Arguments
+/// `path` - Any path inside the working tree of the repository
+///
+/// # Returns
+/// The working tree root and the git directory of the repository, or None
+/// if the path is not inside a git repository
+pub fn find_repository(path: &Path) -> Result<Option<(PathBuf, PathBuf)>> {
+	for dir in path.ancestors() {
+		if let Some(git_dir) = git_dir(dir)? {
+			return Ok(Some((dir.to_owned(), git_dir)));
+		}
+	}
+
+	return Ok(None);
+}
+
+impl GitIndex {
+	/// Reads the index of a git repository
+	///
+	/// # Arguments
+	/// `root`    - The working tree root of the repository
+	/// `git_dir` - The repository's git directory, usually `root/.git`
+	pub fn read(root: &Path, git_dir: &Path) -> Result<GitIndex> {
+		let hash_size = if uses_sha256(git_dir) { 32 } else { 20 };
+
+		let index = match fs::read(git_dir.join("index")) {
+			Ok(data) => parse_index(&data, hash_size)?,
+			// A new repository doesn't have an index until something is staged
+			Err(ref e) if e.kind() == ErrorKind::NotFound => ParsedIndex::default(),
+			Err(e) => return Err(e),
+		};
+
+		let mut paths = index.paths;
+
+		// A split index (`core.splitIndex`) only contains the entries that
+		// changed since the shared index was written, so both are needed.
+		// Entries the split index removes from the shared index are still
+		// included, which at worst keeps a directory that could be cleaned.
+		if let Some(shared_index) = index.shared_index {
+			let data = fs::read(git_dir.join(format!("sharedindex.{}", shared_index)))?;
+			paths.extend(parse_index(&data, hash_size)?.paths);
+			paths.sort();
+			paths.dedup();
+		}
+
+		return Ok(GitIndex {
+			root: root.to_owned(),
+			paths,
+		});
+	}
+
+	/// Checks if the repository tracks any file inside the given directory
+	///
+	/// # Arguments
+	/// `dir` - Absolute path of the directory
+	pub fn tracks_files_in(&self, dir: &Path) -> bool {
+		let relative = match dir.strip_prefix(&self.root) {
+			Ok(relative) => relative,
+			Err(_) => return false,
+		};
+
+		let components: Vec<_> = relative
+			.components()
+			.map(|c| c.as_os_str().to_string_lossy())
+			.collect();
+		if components.is_empty() {
+			return !self.paths.is_empty();
+		}
+
+		let prefix = format!("{}/", components.join("/"));
+
+		// Git sorts entries by their bytes, so all paths starting with the
+		// prefix are grouped together right after the place it would go
+		let start = self
+			.paths
+			.binary_search_by(|p| p.as_bytes().cmp(prefix.as_bytes()))
+			.unwrap_or_else(|i| i);
+
+		return self
+			.paths
+			.get(start)
+			.map(|p| p.starts_with(&prefix))
+			.unwrap_or(false);
+	}
+}
+
+/// Gets the git directory of a working tree root
+///
+/// # Returns
+/// The git directory, or None if `dir` is not the root of a working tree
+fn git_dir(dir: &Path) -> Result<Option<PathBuf>> {
+	let dot_git = dir.join(".git");
+
+	let metadata = match fs::metadata(&dot_git) {
+		Ok(metadata) => metadata,
+		Err(_) => return Ok(None),
+	};
+
+	if metadata.is_dir() {
+		return Ok(Some(dot_git));
+	}
+
+	// Worktrees and submodules have a `.git` file pointing to the actual
+	// git directory, e.g. `gitdir: ../.git/modules/lib`
+	let contents = fs::read_to_string(&dot_git)?;
+	return match contents.trim().strip_prefix("gitdir:") {
+		Some(path) => Ok(Some(dir.join(path.trim()))),
+		None => Err(Error::new(
+			ErrorKind::InvalidData,
+			format!("Invalid .git file in {}", dir.display()),
+		)),
+	};
+}
+
+/// Checks if a repository uses SHA-256 object hashes instead of SHA-1
+fn uses_sha256(git_dir: &Path) -> bool {
+	// Linked worktrees share the config of the main repository
+	let config_dir = match fs::read_to_string(git_dir.join("commondir")) {
+		Ok(common_dir) => git_dir.join(common_dir.trim()),
+		Err(_) => git_dir.to_owned(),
+	};
+
+	let config = fs::read_to_string(config_dir.join("config")).unwrap_or_default();
+	return config.lines().any(|line| {
+		let line: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+		line.eq_ignore_ascii_case("objectformat=sha256")
+	});
+}
+
+/// The parts of an index file needed to find the tracked files
+#[derive(Debug, Default)]
+struct ParsedIndex {
+	/// Paths of all entries in the index
+	paths: Vec<String>,
+
+	/// Hash of the shared index, if this is a split index
+	shared_index: Option<String>,
+}
+
+/// Parses the contents of a git index file (versions 2, 3 and 4)
+///
+/// # Arguments
+/// `data`      - Contents of the index file
+/// `hash_size` - Size of an object hash, 20 for SHA-1 or 32 for SHA-256
+///
+/// # Returns
+/// The paths of all entries in the index, and the shared index it's based
+/// on if it's a split index
+fn parse_index(data: &[u8], hash_size: usize) -> Result<ParsedIndex> {
+	let invalid = |message: &str| Error::new(ErrorKind::InvalidData, message.to_owned());
+
+	if data.len() < 12 || &data[0..4] != b"DIRC" {
+		return Err(invalid("Not a git index file"));
+	}
+
+	let version = read_u32(data, 4);
+	if !(2..=4).contains(&version) {
+		return Err(invalid(&format!(
+			"Unsupported git index version {}",
+			version
+		)));
+	}
+
+	let count = read_u32(data, 8) as usize;
+	let mut paths = Vec::with_capacity(count);
+	let mut previous: Vec<u8> = Vec::new();
+	let mut offset = 12;
+
+	for _ in 0..count {
+		let entry_start = offset;
+		let flags_offset = offset + ENTRY_STAT_SIZE + hash_size;
+		if flags_offset + 2 > data.len() {
+			return Err(invalid("Unexpected end of git index file"));
+		}
+
+		let flags = read_u16(data, flags_offset);
+		offset = flags_offset + 2;
+		if version >= 3 && flags & EXTENDED_FLAG != 0 {
+			offset += 2;
+		}
+
+		// Version 4 only stores the part of the path that differs from the
+		// previous entry, prefixed with how many bytes to strip from it
+		let mut name = Vec::new();
+		if version == 4 {
+			let (strip, size) = read_varint(&data[offset.min(data.len())..])
+				.ok_or_else(|| invalid("Invalid path in git index file"))?;
+			offset += size;
+
+			let keep = previous
+				.len()
+				.checked_sub(strip as usize)
+				.ok_or_else(|| invalid("Invalid path in git index file"))?;
+			name.extend_from_slice(&previous[..keep]);
+		}
+
+		let name_end = data[offset.min(data.len())..]
+			.iter()
+			.position(|b| *b == 0)
+			.map(|i| offset + i)
+			.ok_or_else(|| invalid("Unexpected end of git index file"))?;
+		name.extend_from_slice(&data[offset..name_end]);
+
+		// Versions 2 and 3 pad each entry with 1 to 8 null bytes, so its
+		// size is a multiple of 8
+		offset = if version == 4 {
+			name_end + 1
+		} else {
+			entry_start + ((name_end - entry_start + 8) & !7)
+		};
+
+		// Entries in a split index that replace an entry in the shared index
+		// don't have a path of their own
+		if !name.is_empty() {
+			paths.push(String::from_utf8_lossy(&name).into_owned());
+		}
+		previous = name;
+	}
+
+	// Extensions follow the entries, each starting with a 4 byte signature
+	// and its size, and the file ends with a checksum
+	let mut shared_index = None;
+	let end = data.len().saturating_sub(hash_size);
+	while offset + 8 <= end {
+		let size = read_u32(data, offset + 4) as usize;
+		let content = offset + 8;
+		if content + size > end {
+			return Err(invalid("Invalid extension in git index file"));
+		}
+
+		if &data[offset..offset + 4] == LINK_EXTENSION {
+			if size < hash_size {
+				return Err(invalid("Invalid link extension in git index file"));
+			}
+
+			let hash: String = data[content..content + hash_size]
+				.iter()
+				.map(|b| format!("{:02x}", b))
+				.collect();
+			shared_index = Some(hash);
+		}
+
+		offset = content + size;
+	}
+
+	return Ok(ParsedIndex {
+		paths,
+		shared_index,
+	});
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+	let mut bytes = [0; 4];
+	bytes.copy_from_slice(&data[offset..offset + 4]);
+	u32::from_be_bytes(bytes)
+}
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+	let mut bytes = [0; 2];
+	bytes.copy_from_slice(&data[offset..offset + 2]);
+	u16::from_be_bytes(bytes)
+}
+
+/// Reads a variable-length integer as used in version 4 index files
+///
+/// # Returns
+/// The value and the number of bytes it took up
+fn read_varint(data: &[u8]) -> Option<(u64, usize)> {
+	let mut bytes = data.iter();
+	let mut byte = *bytes.next()?;
+	let mut value = u64::from(byte & 0x7f);
+	let mut size = 1;
+
+	while byte & 0x80 != 0 {
+		byte = *bytes.next()?;
+		value = (value + 1).checked_shl(7)? | u64::from(byte & 0x7f);
+		size += 1;
+	}
+
+	return Some((value, size));
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::utils::test_utils;
+
+	/// Builds an index file containing the given paths
+	fn build_index(version: u32, paths: &[&str]) -> Vec<u8> {
+		let mut data = b"DIRC".to_vec();
+		data.extend_from_slice(&version.to_be_bytes());
+		data.extend_from_slice(&(paths.len() as u32).to_be_bytes());
+
+		let mut previous = "";
+		for path in paths {
+			let entry_start = data.len();
+			data.extend_from_slice(&[0; ENTRY_STAT_SIZE + 20]);
+			data.extend_from_slice(&(path.len() as u16).to_be_bytes());
+
+			if version == 4 {
+				let common = previous
+					.bytes()
+					.zip(path.bytes())
+					.take_while(|(a, b)| a == b)
+					.count();
+				// All test paths are short enough for a single byte varint
+				data.push((previous.len() - common) as u8);
+				data.extend_from_slice(&path.as_bytes()[common..]);
+				data.push(0);
+			} else {
+				data.extend_from_slice(path.as_bytes());
+				let size = (data.len() - entry_start + 8) & !7;
+				data.resize(entry_start + size, 0);
+			}
+
+			previous = path;
+		}
+
+		// Trailing checksum, which isn't verified
+		data.extend_from_slice(&[0; 20]);
+		return data;
+	}
+
+	#[test]
+	fn parse_versions() {
+		let paths = [
+			"build/index.html",
+			"build/main.js",
+			"package.json",
+			"src/index.js",
+		];
+
+		for version in 2..=4 {
+			let parsed =
+				parse_index(&build_index(version, &paths), 20).expect("Could not parse git index");
+			assert_eq!(parsed.paths, paths, "version {}", version);
+			assert_eq!(parsed.shared_index, None);
+		}
+	}
+
+	/// Adds a link extension to an index built by `build_index`, making it a
+	/// split index
+	fn add_link(index: &mut Vec<u8>, shared_index: [u8; 20]) {
+		let checksum = index.split_off(index.len() - 20);
+
+		index.extend_from_slice(LINK_EXTENSION);
+		index.extend_from_slice(&20u32.to_be_bytes());
+		index.extend_from_slice(&shared_index);
+		index.extend_from_slice(&checksum);
+	}
+
+	#[test]
+	fn split_index() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, ".git");
+			test_utils::create_dir(dir, "build");
+			test_utils::create_dir(dir, "dist");
+			test_utils::create_dir(dir, "node_modules");
+
+			let mut index = build_index(2, &["", "dist/index.js", "package.json"]);
+			add_link(&mut index, [0xab; 20]);
+			std::fs::write(dir.join(".git/index"), index).expect("Could not write test file");
+
+			// The shared index is needed to know which files are tracked
+			assert!(GitIndex::read(dir, &dir.join(".git")).is_err());
+
+			std::fs::write(
+				dir.join(format!(".git/sharedindex.{}", "ab".repeat(20))),
+				build_index(2, &["build/index.html", "package.json"]),
+			)
+			.expect("Could not write test file");
+
+			let index = GitIndex::read(dir, &dir.join(".git")).expect("Could not read git index");
+			assert!(index.tracks_files_in(&dir.join("build")));
+			assert!(index.tracks_files_in(&dir.join("dist")));
+			assert!(!index.tracks_files_in(&dir.join("node_modules")));
+		});
+	}
+
+	#[test]
+	fn parse_invalid() {
+		assert!(parse_index(b"", 20).is_err());
+		assert!(parse_index(b"DIRC\0\0\0\x05\0\0\0\0", 20).is_err());
+
+		let mut truncated = build_index(2, &["package.json"]);
+		truncated.truncate(40);
+		assert!(parse_index(&truncated, 20).is_err());
+	}
+
+	#[test]
+	fn varint() {
+		assert_eq!(read_varint(&[0x05]), Some((5, 1)));
+		assert_eq!(read_varint(&[0x80, 0x00]), Some((128, 2)));
+		assert_eq!(read_varint(&[0x80]), None);
+	}
+
+	#[test]
+	fn tracked_dirs() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, ".git");
+			test_utils::create_dir(dir, "build");
+			test_utils::create_dir(dir, "build-cache");
+			test_utils::create_dir(dir, "node_modules");
+			std::fs::write(
+				dir.join(".git/index"),
+				build_index(2, &["build-cache.txt", "build/index.html", "package.json"]),
+			)
+			.expect("Could not write test file");
+
+			let (root, git_dir) = find_repository(&dir.join("build"))
+				.expect("Could not find git repository")
+				.expect("No git repository found");
+			assert_eq!(root, dir);
+			assert_eq!(git_dir, dir.join(".git"));
+
+			let index = GitIndex::read(&root, &git_dir).expect("Could not read git index");
+			assert!(index.tracks_files_in(&dir.join("build")));
+			assert!(!index.tracks_files_in(&dir.join("build-cache")));
+			assert!(!index.tracks_files_in(&dir.join("node_modules")));
+		});
+	}
+
+	#[test]
+	fn no_repository() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "build");
+
+			let index = GitIndex::read(dir, &dir.join(".git")).expect("Could not read git index");
+			assert!(!index.tracks_files_in(&dir.join("build")));
+		});
+	}
+}
@@ -0,0 +1,60 @@
+//! Finds old projects and the directories in them that can be cleaned, like
+//! build output and downloaded dependencies
+//!
+//! This is the library behind the `swp` command line tool. A [`Scanner`]
+//! finds projects and decides which of their directories can be cleaned, and
+//! a [`Cleaner`] deletes them. Nothing is written to the terminal; progress
+//! and problems are reported as [`Event`]s instead.
+//!
+//! # Example
+//! ```no_run
+//! use std::time::Duration;
+//! use swp::{Cleaner, Event, Scanner};
+//!
+//! let scanner = Scanner::new()
+//!     .path("/home/me/projects")
+//!     .older_than(Duration::from_secs(7 * 24 * 60 * 60))
+//!     .on_event(|event| {
+//!         if let Event::Found(project) = event {
+//!             println!("Found {}", project.root().display());
+//!         }
+//!     });
+//!
+//! let cleaner = Cleaner::new();
+//! for project in scanner.scan()? {
+//!     for dir in project.cleanable_dirs() {
+//!         let deletion = cleaner.clean(dir);
+//!         println!("Freed {} bytes in {}", deletion.freed, dir.path.display());
+//!     }
+//! }
+//! # Ok::<(), swp::Error>(())
+//! ```
+
+// This codebase prefers explicit `return` statements and groups each module's
+// main function in a file of the same name
+#![allow(clippy::needless_return, clippy::module_inception)]
+
+pub mod config;
+pub mod rules;
+
+mod cleaner;
+mod error;
+mod event;
+mod git;
+mod project;
+mod scanner;
+mod swpfile;
+mod trash;
+
+mod analyse_projects;
+mod discover_projects;
+mod utils;
+
+pub use crate::cleaner::{Cleaned, Cleaner, Deletion};
+pub use crate::discover_projects::CargoEnv;
+pub use crate::error::{Error, Result};
+pub use crate::event::{Event, SkipReason};
+pub use crate::project::{CleanableDir, Project, Reason, Strategy};
+pub use crate::scanner::{Scanner, DEFAULT_MAX_AGE};
+pub use crate::utils::duration;
+pub use crate::utils::{CancellationToken, RemoveErrors};
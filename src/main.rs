@@ -1,80 +1,377 @@
-use std::fs::remove_dir_all;
-use std::io::{stdin, stdout, Write};
+// This codebase prefers explicit `return` statements
+#![allow(clippy::needless_return)]
 
+use std::io::{stderr, stdin, stdout, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use swp::duration::{format_age, format_duration};
+use swp::{
+	CancellationToken, CleanableDir, Cleaned, Cleaner, Event, Project, SkipReason, Strategy,
+};
 use yansi::{Color, Paint};
 
-use crate::project::Project;
+use crate::progress::{Message, Phase, Progress, Update};
+use crate::report::{Report, Uncleaned};
 use crate::settings::{Settings, SettingsError};
 
 mod output;
-mod project;
+mod progress;
+mod report;
+mod select;
 mod settings;
-mod swpfile;
 
-mod analyse_projects;
-mod discover_projects;
-mod utils;
+// Shared with the library tests, so not all helpers are used here
+#[cfg(test)]
+#[allow(dead_code)]
+#[path = "utils/test_utils.rs"]
+mod test_utils;
+
+/// Exit code used when one or more directories could not be deleted, or
+/// when searching or analysing stopped unexpectedly
+const EXIT_FAILED: i32 = 1;
+
+/// Exit code used in dry-run mode when directories would have been deleted
+const EXIT_DRY_RUN: i32 = 2;
+
+/// Set while waiting for the user to answer a prompt
+static WAITING_FOR_INPUT: AtomicBool = AtomicBool::new(false);
+
+/// Exit code used when the user pressed Ctrl-C, following the shell
+/// convention of 128 + SIGINT
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Number of files owned by other users to list for each directory that
+/// could not be deleted
+const MAX_NOT_OWNED_LISTED: usize = 3;
 
 fn main() {
 	if cfg!(windows) && !Paint::enable_windows_ascii() {
 		Paint::disable();
 	}
 
-	println!(
-		"{} v{}",
-		Paint::new("Sweep").bold(),
-		Paint::new(env!("CARGO_PKG_VERSION")).dimmed()
-	);
+	// The format is set first, so errors in the settings are reported in the
+	// requested format as well
+	let mut settings = Settings::get();
+	output::set_format(settings.format);
 
-	let settings = match Settings::get() {
-		Ok(settings) => settings,
-		Err(err) => {
-			match err {
-				SettingsError::InvalidPath(path) => {
-					output::error(format!("Invalid path: {}", path.to_str().unwrap_or("")))
-				}
+	if let Err(err) = settings.load() {
+		match err {
+			SettingsError::InvalidPath(path) => {
+				output::error(format!("Invalid path: {}", path.to_str().unwrap_or("")))
+			}
+			SettingsError::Unsupported(message) => output::error(message),
+			SettingsError::InvalidConfig(message) if output::is_structured() => {
+				output::error(format!("Invalid config file: {}", message))
+			}
+			SettingsError::InvalidConfig(message) => {
+				output::error("Invalid config file");
+				output::println_info(message);
+			}
+		};
+
+		return;
+	}
+	let report = Arc::new(Mutex::new(Report::new(settings.format)));
+
+	let cancel = CancellationToken::new();
+	let progress = Progress::start();
+	handle_interrupts(&cancel, &report, &progress);
+
+	let exit_code = run(&settings, &report, &cancel, &progress);
+
+	report.lock().unwrap().finish();
+	std::process::exit(exit_code);
+}
+
+/// Makes Ctrl-C stop the application gracefully
+///
+/// The first Ctrl-C cancels the current step, which finishes the directory
+/// it's working on. Pressing Ctrl-C again, or while waiting for input, stops
+/// the application immediately, after writing the records collected so far.
+///
+/// # Arguments
+/// `cancel`   - The token to cancel when Ctrl-C is pressed
+/// `report`   - Collects the machine-readable output
+/// `progress` - Shows the status line, which is removed before stopping
+fn handle_interrupts(cancel: &CancellationToken, report: &Arc<Mutex<Report>>, progress: &Progress) {
+	let cancel = cancel.clone();
+	let report = report.clone();
+	let progress = progress.clone();
+	let result = ctrlc::set_handler(move || {
+		if cancel.is_cancelled() || WAITING_FOR_INPUT.load(Ordering::SeqCst) {
+			// Once the status line is gone it's no longer drawn, so the
+			// prompt that was waiting for input can be cleared as well
+			progress.finish();
+			output::clear_line();
+			output::println("Cancelled", Color::Yellow, "Stopped immediately");
+
+			// A poisoned lock means the main thread panicked, in which case
+			// the records can't be trusted anyway
+			if let Ok(mut report) = report.lock() {
+				report.finish();
+			}
+			std::process::exit(EXIT_INTERRUPTED);
+		}
+
+		cancel.cancel();
+	});
+
+	if let Err(e) = result {
+		output::warning(format!("Ctrl-C can't be handled gracefully: {}", e));
+	}
+}
+
+/// Calls a function that waits for user input, so Ctrl-C stops the
+/// application immediately while it's running
+fn wait_for_input<T, F: FnOnce() -> T>(prompt: F) -> T {
+	WAITING_FOR_INPUT.store(true, Ordering::SeqCst);
+	let result = prompt();
+	WAITING_FOR_INPUT.store(false, Ordering::SeqCst);
+	return result;
+}
+
+/// Prints that the application was stopped before deleting anything
+///
+/// # Returns
+/// The exit code of the process
+fn print_cancelled() -> i32 {
+	output::println("Cancelled", Color::Yellow, "No directories were deleted");
+	return EXIT_INTERRUPTED;
+}
+
+/// Prints that scanning stopped before anything was deleted, because of an
+/// error
+///
+/// # Returns
+/// The exit code of the process
+fn print_failed(error: swp::Error) -> i32 {
+	output::error(error.to_string());
+	output::println_info("No directories were deleted");
+	return EXIT_FAILED;
+}
+
+/// Prints the problems reported while scanning, and passes the progress on
+/// to the status line
+///
+/// # Arguments
+/// `event`    - The event to print
+/// `progress` - Shows the status line
+fn print_event(event: Event, progress: &Progress) {
+	match event {
+		Event::Searching(_) => progress.send(Update::DirSearched),
+		Event::Found(_) => progress.send(Update::ProjectFound),
+		Event::Searched { directories } => {
+			progress.finish();
+			let message = if directories == 1 {
+				"1 directory searched".to_owned()
+			} else {
+				format!("{} directories searched", directories)
+			};
+			output::println("Searched", Color::Green, &message);
+		}
+		Event::SearchFailed(error) => {
+			progress.send(Update::Message(Message::Error(error.to_string()), None))
+		}
+		Event::ProjectSkipped { root, error } => progress.send(Update::Message(
+			Message::Labelled(
+				"Skip",
+				Color::Yellow,
+				root.to_str().unwrap_or("").to_owned(),
+			),
+			Some(error.to_string()),
+		)),
+		Event::ProjectRecent(_) => (),
+		Event::Analysing(_) => progress.send(Update::DirAnalysed),
+		Event::Analysed { recent: 0, .. } => {
+			progress.finish();
+			output::println("Analysed", Color::Green, "All projects can be cleaned");
+		}
+		Event::Analysed { cleanable: 0, .. } => {
+			progress.finish();
+			output::println(
+				"Analysed",
+				Color::Green,
+				"All projects have been modified recently",
+			);
+		}
+		Event::Analysed { cleanable, recent } => {
+			progress.finish();
+			let message = format!(
+				"{} of {} projects can be cleaned",
+				cleanable,
+				cleanable + recent
+			);
+			output::println("Analysed", Color::Green, &message);
+			output::println_info(format!("{} projects have been modified recently", recent));
+		}
+		Event::Skipped { path, reason } => {
+			let (message, info) = match reason {
+				SkipReason::TrackedByGit => (
+					Message::Warning(format!(
+						"Skipping {}, it contains files tracked by git",
+						path.display()
+					)),
+					None,
+				),
+				SkipReason::GitFailed(error) => (
+					Message::Warning(format!(
+						"Skipping {}, could not check if it contains files tracked by git",
+						path.display()
+					)),
+					Some(error.to_string()),
+				),
+				SkipReason::UsedBy(root) => (
+					Message::Labelled(
+						"Skip",
+						Color::Yellow,
+						format!(
+							"{} is also used by {}, which was modified recently",
+							path.display(),
+							root.display()
+						),
+					),
+					None,
+				),
 			};
 
-			return;
+			progress.send(Update::Message(message, info));
 		}
-	};
+		Event::MeasureStarted { directories } => {
+			progress.send(Update::Start(Phase::Measuring { directories }))
+		}
+		Event::Measuring { bytes, .. } => progress.send(Update::BytesMeasured(bytes)),
+		Event::DirMeasured { .. } => progress.send(Update::DirMeasured),
+		Event::Measured { directories } => {
+			progress.finish();
+			let message = if directories == 1 {
+				"1 directory measured".to_owned()
+			} else {
+				format!("{} directories measured", directories)
+			};
+			output::println("Measured", Color::Green, &message);
+		}
+	}
+}
+
+/// Runs the application
+///
+/// # Arguments
+/// `settings` - The application settings
+/// `report`   - Collects the machine-readable output
+/// `cancel`   - Cancelled when the user presses Ctrl-C
+/// `progress` - Shows the status line
+///
+/// # Returns
+/// The exit code of the process
+fn run(
+	settings: &Settings,
+	report: &Mutex<Report>,
+	cancel: &CancellationToken,
+	progress: &Progress,
+) -> i32 {
+	if !output::is_structured() {
+		println!(
+			"{} v{}",
+			Paint::new("Sweep").bold(),
+			Paint::new(env!("CARGO_PKG_VERSION")).dimmed()
+		);
+	}
 
 	for path in &settings.paths {
 		output::println("Path", Color::Blue, path.to_str().unwrap_or(""));
 	}
 
+	let uncleaned = Arc::new(Mutex::new(Uncleaned::default()));
+	let scanner = {
+		let progress = progress.clone();
+		let uncleaned = uncleaned.clone();
+		settings.scanner(cancel).on_event(move |event| {
+			uncleaned.lock().unwrap().record(&event);
+			print_event(event, &progress);
+		})
+	};
+
 	// Discover cleanable projects
-	let cleanables = match discover_projects::discover_projects(&settings) {
-		Some(cleanables) => cleanables,
-		None => {
-			output::println_plain(Some(Color::Yellow), "No sweepable projects found");
-			output::println_plain(None, "  Check your paths and try again.");
-			output::println_plain(None, "  See `--help` for more options");
-			return;
-		}
+	progress.send(Update::Start(Phase::Searching));
+	let discovered = scanner.discover();
+	progress.finish();
+
+	let cleanables = match discovered {
+		_ if cancel.is_cancelled() => return print_cancelled(),
+		Ok(cleanables) => cleanables,
+		Err(e) => return print_failed(e),
 	};
 
+	if cleanables.is_empty() {
+		report
+			.lock()
+			.unwrap()
+			.discovered_projects(&[], &[], &uncleaned.lock().unwrap());
+		output::println_plain(Some(Color::Yellow), "No sweepable projects found");
+		output::println_plain(None, "  Check your paths and try again.");
+		output::println_plain(None, "  See `--help` for more options");
+		return 0;
+	}
+
 	output::println_info(format!("{} sweepable projects found", cleanables.len()));
 
+	if settings.all {
+		output::println(
+			"Skip",
+			Color::Yellow,
+			"--all flag set, ignoring last used time",
+		);
+	}
+
+	let discovered: Vec<(PathBuf, Vec<String>)> = cleanables
+		.iter()
+		.map(|project| (project.root().to_owned(), project.ecosystems().to_vec()))
+		.collect();
+
 	// Figure out which directories can be deleted
-	let delete_dirs = analyse_projects::analyse_projects(cleanables, &settings);
+	if !settings.all {
+		progress.send(Update::Start(Phase::Analysing));
+	}
+	let analysed = scanner.analyse(cleanables);
+	progress.finish();
+
+	let projects = match analysed {
+		_ if cancel.is_cancelled() => return print_cancelled(),
+		Ok(projects) => projects,
+		Err(e) => return print_failed(e),
+	};
+
+	report
+		.lock()
+		.unwrap()
+		.discovered_projects(&projects, &discovered, &uncleaned.lock().unwrap());
 
-	if delete_dirs.len() == 0 {
+	let delete_dirs: Vec<&CleanableDir> = projects
+		.iter()
+		.flat_map(|project| project.cleanable_dirs())
+		.collect();
+
+	if delete_dirs.is_empty() {
 		output::println_plain(Some(Color::Yellow), "No sweepable projects found");
 		output::println_plain(
 			None,
-			"  This is likely because your projects were recently modified",
+			format!(
+				"  This is likely because your projects were modified in the last {}",
+				format_duration(settings.max_age())
+			),
 		);
 		output::println_plain(
 			None,
-			"  Run the application with `--all` to disregard file age",
+			"  Run the application with `--all` to disregard file age, or set a shorter `--older-than`",
 		);
 		output::println_plain(None, "  See `--help` for more options");
-		return;
+		return 0;
 	}
 
 	let message = if delete_dirs.len() == 1 {
-		format!("Found 1 directory that can be deleted:")
+		"Found 1 directory that can be deleted:".to_owned()
 	} else {
 		format!(
 			"Found {} directories that can be deleted:",
@@ -83,59 +380,426 @@ fn main() {
 	};
 
 	output::println("Result", Color::Green, &message);
-	for dir in &delete_dirs {
-		output::println_info(dir.to_str().unwrap_or(""));
+	for project in &projects {
+		for dir in project.cleanable_dirs() {
+			let pruned = match &dir.strategy {
+				Strategy::Remove if dir.is_symlink() => {
+					" (symlink, only the link is removed)".to_owned()
+				}
+				Strategy::Remove => String::new(),
+				Strategy::Prune(paths) => format!(" ({} stale artifacts)", paths.len()),
+			};
+
+			output::println_info(format!(
+				"{:>10}  {}{}",
+				output::format_size(dir.size),
+				dir.path.to_str().unwrap_or(""),
+				pruned
+			));
+
+			if settings.dry_run {
+				output::println_info(format!("{:>10}  {}", "", describe_reason(project, dir)));
+			}
+		}
+	}
+
+	let total_size: u64 = delete_dirs.iter().map(|dir| dir.size).sum();
+	output::println(
+		"Total",
+		Color::Green,
+		&format!("{} can be freed", output::format_size(total_size)),
+	);
+
+	if settings.dry_run {
+		output::println("Dry run", Color::Yellow, "No directories were deleted");
+		return EXIT_DRY_RUN;
 	}
 
-	if !settings.force {
+	let delete_dirs = if settings.interactive {
+		// Choosing the directories in the checklist replaces the y/n prompt
+		let selected = match wait_for_input(|| select::select_dirs(&projects)) {
+			Some(selected) => selected,
+			None => return 0,
+		};
+
+		let delete_dirs: Vec<&CleanableDir> = delete_dirs
+			.into_iter()
+			.filter(|dir| selected.contains(&dir.path))
+			.collect();
+
+		if delete_dirs.is_empty() {
+			output::println_plain(Some(Color::Yellow), "No directories selected");
+			return 0;
+		}
+
+		delete_dirs
+	} else {
+		if !settings.force && !wait_for_input(|| confirm(settings.trash)) {
+			return 0;
+		}
+
+		delete_dirs
+	};
+
+	// Keep going when a directory can't be deleted, so one bad directory
+	// doesn't prevent all others from being cleaned
+	let cleaner = Cleaner::new()
+		.trash(settings.trash)
+		.cancel_token(cancel.clone());
+	progress.send(Update::Start(Phase::Deleting {
+		directories: delete_dirs.len(),
+		bytes: delete_dirs.iter().map(|dir| dir.size).sum(),
+		trash: settings.trash,
+	}));
+
+	let cleaned = cleaner.clean_all(&delete_dirs, |dir, deletion| {
+		progress.send(Update::DirDeleted(deletion.freed));
+
+		report.lock().unwrap().deletion(
+			&dir.path,
+			deletion.freed,
+			settings.trash,
+			deletion.errors.as_ref().map(|errors| errors.to_string()),
+			deletion
+				.errors
+				.as_ref()
+				.map(|errors| &errors.not_owned[..])
+				.unwrap_or_default(),
+		);
+	});
+
+	progress.finish();
+
+	for dir in &cleaned.skipped {
+		report
+			.lock()
+			.unwrap()
+			.cancelled_deletion(&dir.path, settings.trash);
+	}
+
+	return print_deletion_summary(&cleaned, settings.trash);
+}
+
+/// Prints the outcome of the delete operation
+///
+/// If all directories were deleted this is a single line, otherwise each
+/// directory is listed along with the error that occured.
+///
+/// # Arguments
+/// `cleaned` - The outcome of each directory, and the directories that
+///             weren't deleted because Ctrl-C was pressed
+/// `trash`   - True if the directories were moved to the trash
+///
+/// # Returns
+/// The exit code of the process
+fn print_deletion_summary(cleaned: &Cleaned, trash: bool) -> i32 {
+	let skipped = &cleaned.skipped;
+
+	let (label, done, freed_message) = if trash {
+		("Trashed", "trashed", "moved to the trash")
+	} else {
+		("Deleted", "deleted", "freed")
+	};
+
+	if cleaned.is_complete() {
+		output::println(
+			label,
+			Color::Green,
+			&format!(
+				"All directories {}, {} {}",
+				done,
+				output::format_size(cleaned.freed()),
+				freed_message
+			),
+		);
+		return 0;
+	}
+
+	for (dir, deletion) in &cleaned.results {
+		let message = format!(
+			"{:>10}  {}",
+			output::format_size(deletion.freed),
+			dir.path.to_str().unwrap_or("")
+		);
+
+		match &deletion.errors {
+			None => output::println(label, Color::Green, &message),
+			Some(errors) => {
+				output::println("Failed", Color::Red, &message);
+				output::println_info(format!("{:>10}  {}", "", errors));
+
+				// These can only be deleted by their owner, so list some of
+				// them to show the user where to look
+				for path in errors.not_owned.iter().take(MAX_NOT_OWNED_LISTED) {
+					output::println_info(format!(
+						"{:>10}  owned by another user: {}",
+						"",
+						path.display()
+					));
+				}
+				if errors.not_owned.len() > MAX_NOT_OWNED_LISTED {
+					output::println_info(format!(
+						"{:>10}  and {} more",
+						"",
+						errors.not_owned.len() - MAX_NOT_OWNED_LISTED
+					));
+				}
+			}
+		}
+	}
+
+	for dir in skipped {
+		output::println(
+			"Skipped",
+			Color::Yellow,
+			&format!(
+				"{:>10}  {}",
+				output::format_size(dir.size),
+				dir.path.to_str().unwrap_or("")
+			),
+		);
+	}
+
+	output::println(
+		"Summary",
+		Color::Yellow,
+		&summarise_deletions(cleaned, trash),
+	);
+
+	if !skipped.is_empty() {
+		output::println_info(format!(
+			"Cancelled, {} directories were not {}",
+			skipped.len(),
+			done
+		));
+
+		if output::is_structured() {
+			eprintln!(
+				"Error: cancelled, {} directories were not {}",
+				skipped.len(),
+				done
+			);
+		}
+
+		return EXIT_INTERRUPTED;
+	}
+
+	if output::is_structured() {
+		eprintln!(
+			"Error: {} directories could not be deleted",
+			cleaned.failed()
+		);
+	}
+
+	return EXIT_FAILED;
+}
+
+/// Describes how many directories were deleted and how much space that
+/// freed, counting directories that were only partially deleted as failed
+///
+/// # Arguments
+/// `cleaned` - The outcome of deleting the directories
+/// `trash`   - True if the directories were moved to the trash
+fn summarise_deletions(cleaned: &Cleaned, trash: bool) -> String {
+	let failed = cleaned.failed();
+	let skipped = cleaned.skipped.len();
+
+	let (done, freed_message) = if trash {
+		("trashed", "moved to the trash")
+	} else {
+		("deleted", "freed")
+	};
+
+	let mut summary = format!(
+		"{} of {} directories {}",
+		cleaned.cleaned(),
+		cleaned.results.len() + skipped,
+		done
+	);
+	if failed > 0 {
+		summary = format!("{}, {} failed", summary, failed);
+	}
+	if skipped > 0 {
+		summary = format!("{}, {} skipped", summary, skipped);
+	}
+
+	return format!(
+		"{}, {} {}",
+		summary,
+		output::format_size(cleaned.freed()),
+		freed_message
+	);
+}
+
+/// Asks the user to confirm before deleting the listed directories
+///
+/// When writing structured output, the prompt is written to stderr so
+/// stdout only contains the records.
+///
+/// # Arguments
+/// `trash` - True if the directories will be moved to the trash
+///
+/// # Returns
+/// True if the user confirmed
+fn confirm(trash: bool) -> bool {
+	let message = if trash {
+		"Above directories will be moved to the trash"
+	} else {
+		"Above directories will be permanently deleted"
+	};
+
+	if output::is_structured() {
+		eprintln!("{}", message);
+	} else if trash {
+		println!(
+			"{}{} {}",
+			" ".repeat(output::LABEL_WIDTH - 7),
+			Paint::black(" TRASH ").bold().bg(Color::Yellow),
+			Paint::yellow(message).bold()
+		);
+	} else {
 		println!(
 			"{}{} {}",
 			" ".repeat(output::LABEL_WIDTH - 8),
 			Paint::white(" DANGER ").bold().bg(Color::Red),
-			Paint::red("Above directories will be permanently deleted").bold()
+			Paint::red(message).bold()
 		);
+	}
 
-		loop {
+	loop {
+		if output::is_structured() {
+			eprint!("Continue? (y/n): ");
+			stderr().flush().unwrap();
+		} else {
 			print!(
 				"{} {} (y/n): ",
 				" ".repeat(output::LABEL_WIDTH),
 				Paint::new("Continue?").bold()
 			);
 			stdout().flush().unwrap();
+		}
 
-			let mut input = String::new();
-			stdin()
-				.read_line(&mut input)
-				.expect("Could not read CLI input");
-			let input = input.trim();
+		let mut input = String::new();
+		stdin()
+			.read_line(&mut input)
+			.expect("Could not read CLI input");
+		let input = input.trim();
 
-			if input == "n" {
-				return;
-			}
+		if input == "n" {
+			return false;
+		}
 
-			if input == "y" {
-				break;
-			}
+		if input == "y" {
+			return true;
+		}
 
+		if output::is_structured() {
+			eprintln!("Please answer either 'y' or 'n'");
+		} else {
 			output::println_info("Please answer either 'y' or 'n'");
 		}
 	}
+}
 
-	for dir in delete_dirs {
-		output::print("Deleting", Color::Cyan, dir.to_str().unwrap_or(""));
-		match remove_dir_all(&dir) {
-			Err(error) => {
-				println!();
-				output::error(format!(
-					"Could not delete directory {}",
-					&dir.to_str().unwrap_or("")
-				));
-				output::println_info(error.to_string());
-				return;
-			}
-			_ => (),
+/// Describes why a directory was marked as cleanable, for the dry-run output
+fn describe_reason(project: &Project, dir: &CleanableDir) -> String {
+	let mut reason = dir.reason.to_string();
+
+	if project.last_modified() != 0 {
+		reason = format!(
+			"{}, project last modified {}",
+			reason,
+			format_age(project.last_modified())
+		);
+	}
+
+	if !dir.shared_with.is_empty() {
+		let others: Vec<_> = dir
+			.shared_with
+			.iter()
+			.map(|root| root.display().to_string())
+			.collect();
+		reason = format!("{}, also used by {}", reason, others.join(", "));
+	}
+
+	return reason;
+}
+
+#[cfg(test)]
+mod test {
+	use std::io;
+
+	use swp::{Deletion, Reason, RemoveErrors};
+
+	use super::*;
+
+	fn dir(path: &str) -> CleanableDir {
+		CleanableDir {
+			path: PathBuf::from(path),
+			reason: Reason::marker("package.json"),
+			size: 4096,
+			shared_with: Vec::new(),
+			strategy: Strategy::Remove,
 		}
 	}
 
-	output::println("Deleted", Color::Green, "All directories deleted");
+	fn partial_failure<'a>(a: &'a CleanableDir, b: &'a CleanableDir) -> Cleaned<'a> {
+		let errors = RemoveErrors {
+			not_owned: vec![PathBuf::from("/b/node_modules/root-owned")],
+			failed: vec![(
+				PathBuf::from("/b/node_modules/.bin"),
+				io::Error::from(io::ErrorKind::PermissionDenied),
+			)],
+		};
+
+		return Cleaned {
+			results: vec![
+				(
+					a,
+					Deletion {
+						freed: 3072,
+						errors: None,
+					},
+				),
+				(
+					b,
+					Deletion {
+						freed: 1024,
+						errors: Some(errors),
+					},
+				),
+			],
+			skipped: Vec::new(),
+		};
+	}
+
+	#[test]
+	fn deletion_summary() {
+		let (a, b, c) = (dir("/a/target"), dir("/b/node_modules"), dir("/c/build"));
+		let mut cleaned = partial_failure(&a, &b);
+
+		// Bytes freed before an error occured still count
+		assert_eq!(
+			summarise_deletions(&cleaned, false),
+			"1 of 2 directories deleted, 1 failed, 4.0 KiB freed"
+		);
+
+		cleaned.skipped = vec![&c, &c, &c];
+		assert_eq!(
+			summarise_deletions(&cleaned, true),
+			"1 of 5 directories trashed, 1 failed, 3 skipped, 4.0 KiB moved to the trash"
+		);
+	}
+
+	#[test]
+	fn deletion_exit_code() {
+		let (a, b) = (dir("/a/target"), dir("/b/node_modules"));
+		let mut cleaned = partial_failure(&a, &b);
+		assert_eq!(print_deletion_summary(&cleaned, false), EXIT_FAILED);
+
+		cleaned
+			.results
+			.retain(|(_, deletion)| deletion.errors.is_none());
+		assert_eq!(print_deletion_summary(&cleaned, false), 0);
+	}
 }
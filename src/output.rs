@@ -1,62 +1,152 @@
 use std::io::{stdout, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use yansi::{Color, Paint, Style};
 
 pub const LABEL_WIDTH: usize = 12;
 
+/// Set when the output format is structured, in which case stdout is
+/// reserved for the records written by `Report`
+static STRUCTURED: AtomicBool = AtomicBool::new(false);
+
+/// The format in which results are written to stdout
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+	/// Human-readable terminal output
+	Text,
+
+	/// A single JSON document, written when the application finishes
+	Json,
+
+	/// One JSON record per line, written as soon as it's available
+	Ndjson,
+}
+
+impl FromStr for Format {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Format, String> {
+		match s {
+			"text" => Ok(Format::Text),
+			"json" => Ok(Format::Json),
+			"ndjson" => Ok(Format::Ndjson),
+			_ => Err(format!("Unknown format '{}'", s)),
+		}
+	}
+}
+
+/// Sets the output format
+///
+/// In structured formats, the terminal output functions in this module
+/// don't print anything and errors are written to stderr instead.
+pub fn set_format(format: Format) {
+	STRUCTURED.store(format != Format::Text, Ordering::SeqCst);
+}
+
+/// Checks if the output format is JSON or NDJSON
+pub fn is_structured() -> bool {
+	STRUCTURED.load(Ordering::SeqCst)
+}
+
 pub fn error<S: Into<String>>(message: S) {
+	if is_structured() {
+		eprintln!("Error: {}", message.into());
+		return;
+	}
+
 	println("Error", Color::Red, &message.into());
 }
 
+/// Prints a warning, which is written to stderr in structured formats so
+/// it's still seen
+pub fn warning<S: Into<String>>(message: S) {
+	if is_structured() {
+		eprintln!("Warning: {}", message.into());
+		return;
+	}
+
+	println("Warning", Color::Yellow, &message.into());
+}
+
 pub fn println<S: Into<String>>(label: S, label_colour: Color, message: S) {
-	print(label, label_colour, message);
-	println!();
+	write(
+		format_labelled(label.into(), label_colour, message.into()),
+		"\n",
+	);
 }
 
 pub fn println_info<S: Into<String>>(message: S) {
-	print_info(message);
-	println!();
+	write(format_info(message.into()), "\n");
 }
 
 pub fn println_plain<S: Into<String>>(colour: Option<Color>, message: S) {
-	print_plain(colour, message);
-	println!();
+	write(format_plain(colour, message.into()), "\n");
 }
 
+/// Clears a line that was printed with one of the `print` functions, which
+/// would otherwise be left half overwritten when exiting
+pub fn clear_line() {
+	if is_structured() {
+		return;
+	}
+
+	write(format!("\r{}", " ".repeat(get_term_width())), "\r");
+}
+
+/// Prints a line that is overwritten by the next output, e.g. a status line
 pub fn print<S: Into<String>>(label: S, label_colour: Color, message: S) {
+	write(
+		format_labelled(label.into(), label_colour, message.into()),
+		"\r",
+	);
+}
+
+/// Writes a formatted line and its line ending in one go, so lines printed
+/// from different threads at the same time don't get mixed up
+fn write(line: String, end: &str) {
+	if is_structured() {
+		return;
+	}
+
+	let stdout = stdout();
+	let mut stdout = stdout.lock();
+	write!(stdout, "{}{}", line, end).unwrap();
+	stdout.flush().unwrap();
+}
+
+fn format_labelled(label: String, label_colour: Color, message: String) -> String {
 	let term_width = get_term_width();
-	let message = shorten(message.into(), term_width - LABEL_WIDTH - 1);
-	let label = label.into();
+	let message = shorten(message, term_width - LABEL_WIDTH - 1);
 
 	if label.len() > LABEL_WIDTH {
-		panic!(format!("Label {} too long", label));
+		panic!("Label {} too long", label);
 	}
 
-	print!(
-		"{}{} {}{}\r",
+	return format!(
+		"{}{} {}{}",
 		" ".repeat(LABEL_WIDTH - label.len()),
 		Style::new(label_colour).bold().paint(label),
 		message,
 		" ".repeat(term_width - LABEL_WIDTH - message.len() - 1),
 	);
-	stdout().flush().unwrap();
 }
 
-pub fn print_info<S: Into<String>>(message: S) {
+fn format_info(message: String) -> String {
 	let term_width = get_term_width();
-	let message = shorten(message.into(), term_width - LABEL_WIDTH - 1);
+	let message = shorten(message, term_width - LABEL_WIDTH - 1);
 
-	print!(
-		"{} {}{}\r",
+	return format!(
+		"{} {}{}",
 		" ".repeat(LABEL_WIDTH),
 		message,
 		" ".repeat(term_width - LABEL_WIDTH - message.len() - 1),
 	);
-	stdout().flush().unwrap();
 }
 
-pub fn print_plain<S: Into<String>>(colour: Option<Color>, message: S) {
+fn format_plain(colour: Option<Color>, message: String) -> String {
 	let term_width = get_term_width();
-	let message = shorten(message.into(), term_width - LABEL_WIDTH - 1);
+	let message = shorten(message, term_width - LABEL_WIDTH - 1);
 	let message_len = message.len();
 
 	let message = match colour {
@@ -64,8 +154,25 @@ pub fn print_plain<S: Into<String>>(colour: Option<Color>, message: S) {
 		None => Paint::new(message),
 	};
 
-	print!("{}{}\r", message, " ".repeat(term_width - message_len),);
-	stdout().flush().unwrap();
+	return format!("{}{}", message, " ".repeat(term_width - message_len));
+}
+
+/// Formats a number of bytes as a human-readable size, e.g. `1.4 GiB`
+pub fn format_size(bytes: u64) -> String {
+	const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+	if bytes < 1024 {
+		return format!("{} B", bytes);
+	}
+
+	let mut size = bytes as f64 / 1024.0;
+	let mut unit = 0;
+	while size >= 1024.0 && unit < UNITS.len() - 1 {
+		size /= 1024.0;
+		unit += 1;
+	}
+
+	return format!("{:.1} {}", size, UNITS[unit]);
 }
 
 /// Shortens a message by omitting the middle part and replacing it with '...'
@@ -99,3 +206,17 @@ fn get_term_width() -> usize {
 		80
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::format_size;
+
+	#[test]
+	fn sizes() {
+		assert_eq!(format_size(0), "0 B");
+		assert_eq!(format_size(1023), "1023 B");
+		assert_eq!(format_size(1024), "1.0 KiB");
+		assert_eq!(format_size(1536), "1.5 KiB");
+		assert_eq!(format_size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+	}
+}
@@ -0,0 +1,386 @@
+use std::io::{stdout, IsTerminal};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crossbeam::channel::{self, Receiver, RecvTimeoutError, Sender};
+use yansi::Color;
+
+use crate::output;
+
+/// How often the status line is redrawn when stdout is a terminal
+const REDRAW_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How often a progress line is printed when stdout is not a terminal, e.g.
+/// when it's redirected to a log file
+const PLAIN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A step of the application that shows its progress while it's running
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+	Searching,
+	Analysing,
+
+	/// Measuring the given number of directories
+	Measuring {
+		directories: usize,
+	},
+
+	/// Deleting (or trashing) the given number of directories, which add up
+	/// to `bytes`
+	Deleting {
+		directories: usize,
+		bytes: u64,
+		trash: bool,
+	},
+}
+
+/// Progress made in the current phase
+#[derive(Debug)]
+pub enum Update {
+	/// Shows the status line for a phase, starting with all counts at 0
+	Start(Phase),
+
+	DirSearched,
+	ProjectFound,
+	DirAnalysed,
+
+	/// Measured the files in a directory, which take up the given number of
+	/// bytes
+	BytesMeasured(u64),
+
+	/// Finished measuring one of the directories
+	DirMeasured,
+
+	/// Deleted a directory, freeing the given number of bytes
+	DirDeleted(u64),
+
+	/// Prints a message above the status line, optionally followed by an
+	/// indented line with more information
+	Message(Message, Option<String>),
+
+	/// Removes the status line, and replies once it's gone
+	Finish(Sender<()>),
+}
+
+/// A line printed while a phase is running, e.g. a problem found while
+/// scanning
+#[derive(Debug)]
+pub enum Message {
+	Error(String),
+	Warning(String),
+	Labelled(&'static str, Color, String),
+}
+
+impl Message {
+	fn print(self) {
+		match self {
+			Message::Error(message) => output::error(message),
+			Message::Warning(message) => output::warning(message),
+			Message::Labelled(label, colour, message) => output::println(label, colour, &message),
+		}
+	}
+}
+
+/// Shows the progress of the current phase on a single status line
+///
+/// Updates can be sent from any thread. They're all drawn by one renderer
+/// thread, so the status line isn't overwritten by several threads at once.
+/// Other output while a phase is running should be sent as a
+/// `Update::Message` for the same reason.
+/// When stdout is not a terminal, a plain line is printed every few seconds
+/// instead.
+#[derive(Clone)]
+pub struct Progress {
+	sender: Sender<Update>,
+}
+
+impl Progress {
+	/// Starts the renderer thread, which stops when all copies of the
+	/// returned `Progress` have been dropped
+	pub fn start() -> Progress {
+		let (sender, receiver) = channel::unbounded();
+		let terminal = stdout().is_terminal();
+
+		thread::spawn(move || render(receiver, terminal));
+
+		return Progress { sender };
+	}
+
+	pub fn send(&self, update: Update) {
+		// The renderer only stops when there are no senders left
+		let _ = self.sender.send(update);
+	}
+
+	/// Removes the status line, so other output can be printed after it
+	///
+	/// Waits until the line has been removed, so it can't be drawn over the
+	/// next output.
+	pub fn finish(&self) {
+		let (done, wait) = channel::bounded(1);
+		self.send(Update::Finish(done));
+		let _ = wait.recv();
+	}
+}
+
+/// Receives updates and draws the status line until there are no senders
+/// left
+///
+/// # Arguments
+/// `receiver` - Receives the updates
+/// `terminal` - True if stdout is a terminal, false to print plain lines
+fn render(receiver: Receiver<Update>, terminal: bool) {
+	let interval = if terminal {
+		REDRAW_INTERVAL
+	} else {
+		PLAIN_INTERVAL
+	};
+
+	let mut status: Option<Status> = None;
+	let mut next_draw = Instant::now();
+
+	loop {
+		let update = if status.is_some() {
+			receiver.recv_timeout(next_draw.saturating_duration_since(Instant::now()))
+		} else {
+			receiver.recv().map_err(|_| RecvTimeoutError::Disconnected)
+		};
+
+		match update {
+			Ok(Update::Start(phase)) => {
+				status = Some(Status::new(phase));
+
+				// Plain lines are only printed for phases that take a while
+				next_draw = if terminal {
+					Instant::now()
+				} else {
+					Instant::now() + interval
+				};
+			}
+			Ok(Update::Message(message, info)) => {
+				// The status line is drawn again right below the message
+				if status.is_some() && terminal {
+					output::clear_line();
+					next_draw = Instant::now();
+				}
+
+				message.print();
+				if let Some(info) = info {
+					output::println_info(info);
+				}
+			}
+			Ok(Update::Finish(done)) => {
+				if status.take().is_some() && terminal {
+					output::clear_line();
+				}
+
+				let _ = done.send(());
+			}
+			Ok(update) => {
+				if let Some(status) = &mut status {
+					status.update(update);
+				}
+			}
+			Err(RecvTimeoutError::Timeout) => (),
+			Err(RecvTimeoutError::Disconnected) => return,
+		}
+
+		if let Some(status) = &status {
+			if Instant::now() >= next_draw {
+				let (label, message) = status.describe(status.started.elapsed());
+				if terminal {
+					output::print(label, Color::Cyan, &message);
+				} else {
+					output::println(label, Color::Cyan, &message);
+				}
+
+				next_draw = Instant::now() + interval;
+			}
+		}
+	}
+}
+
+/// The counts shown on the status line
+struct Status {
+	phase: Phase,
+	started: Instant,
+
+	/// Directories searched, analysed, measured or deleted
+	directories: usize,
+
+	/// Directories that have been measured completely
+	finished: usize,
+
+	projects: usize,
+
+	/// Bytes measured or freed
+	bytes: u64,
+}
+
+impl Status {
+	fn new(phase: Phase) -> Status {
+		Status {
+			phase,
+			started: Instant::now(),
+			directories: 0,
+			finished: 0,
+			projects: 0,
+			bytes: 0,
+		}
+	}
+
+	fn update(&mut self, update: Update) {
+		match update {
+			Update::DirSearched | Update::DirAnalysed => self.directories += 1,
+			Update::ProjectFound => self.projects += 1,
+			Update::BytesMeasured(bytes) => {
+				self.directories += 1;
+				self.bytes += bytes;
+			}
+			Update::DirMeasured => self.finished += 1,
+			Update::DirDeleted(freed) => {
+				self.directories += 1;
+				self.bytes += freed;
+			}
+			Update::Start(_) | Update::Message(..) | Update::Finish(_) => (),
+		}
+	}
+
+	/// Describes the progress so far
+	///
+	/// # Arguments
+	/// `elapsed` - How long the phase has been running
+	///
+	/// # Returns
+	/// The label and message of the status line
+	fn describe(&self, elapsed: Duration) -> (&'static str, String) {
+		match self.phase {
+			Phase::Searching => (
+				"Searching",
+				format!(
+					"{} directories searched, {} projects found",
+					self.directories, self.projects
+				),
+			),
+			Phase::Analysing => (
+				"Analysing",
+				format!("{} directories checked", self.directories),
+			),
+			Phase::Measuring { directories } => {
+				let message = format!(
+					"{} of {} directories, {}{}{}",
+					self.finished,
+					directories,
+					output::format_size(self.bytes),
+					format_throughput(self.bytes, elapsed),
+					format_eta(elapsed, self.finished as f64, directories as f64)
+				);
+
+				("Measuring", message)
+			}
+			Phase::Deleting {
+				directories,
+				bytes,
+				trash,
+			} => {
+				// Directories can differ a lot in size, so the estimate is
+				// based on the bytes freed if the sizes are known
+				let eta = if bytes > 0 {
+					format_eta(elapsed, self.bytes as f64, bytes as f64)
+				} else {
+					format_eta(elapsed, self.directories as f64, directories as f64)
+				};
+
+				let message = format!(
+					"{} of {} directories, {} of {}{}{}",
+					self.directories,
+					directories,
+					output::format_size(self.bytes),
+					output::format_size(bytes),
+					format_throughput(self.bytes, elapsed),
+					eta
+				);
+
+				(if trash { "Trashing" } else { "Deleting" }, message)
+			}
+		}
+	}
+}
+
+/// Formats the number of bytes handled per second, e.g. ` (12.0 MiB/s)`
+///
+/// # Returns
+/// The throughput, or an empty string if it can't be calculated yet
+fn format_throughput(bytes: u64, elapsed: Duration) -> String {
+	if elapsed < Duration::from_secs(1) {
+		return String::new();
+	}
+
+	let per_second = bytes as f64 / elapsed.as_secs_f64();
+	return format!(" ({}/s)", output::format_size(per_second as u64));
+}
+
+/// Estimates how long the rest of a phase will take, assuming it continues
+/// at the same rate, e.g. `, about 1m 05s left`
+///
+/// # Arguments
+/// `elapsed` - How long the phase has been running
+/// `done`    - How much work has been done
+/// `total`   - How much work there is in total
+///
+/// # Returns
+/// The estimate, or an empty string if nothing has been done yet
+fn format_eta(elapsed: Duration, done: f64, total: f64) -> String {
+	if done <= 0.0 || done >= total || elapsed < Duration::from_secs(1) {
+		return String::new();
+	}
+
+	let secs = (elapsed.as_secs_f64() * (total - done) / done).ceil() as u64;
+
+	let remaining = if secs < 60 {
+		format!("{}s", secs)
+	} else if secs < 60 * 60 {
+		format!("{}m {:02}s", secs / 60, secs % 60)
+	} else {
+		format!("{}h {:02}m", secs / 60 / 60, secs / 60 % 60)
+	};
+
+	return format!(", about {} left", remaining);
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn describe() {
+		let mut status = Status::new(Phase::Measuring { directories: 4 });
+		status.update(Update::BytesMeasured(3 * 1024 * 1024));
+		status.update(Update::DirMeasured);
+
+		let (label, message) = status.describe(Duration::from_secs(2));
+		assert_eq!(label, "Measuring");
+		assert_eq!(
+			message,
+			"1 of 4 directories, 3.0 MiB (1.5 MiB/s), about 6s left"
+		);
+
+		let mut status = Status::new(Phase::Deleting {
+			directories: 3,
+			bytes: 4096,
+			trash: true,
+		});
+		status.update(Update::DirDeleted(1024));
+
+		let (label, message) = status.describe(Duration::from_secs(90));
+		assert_eq!(label, "Trashing");
+		assert_eq!(
+			message,
+			"1 of 3 directories, 1.0 KiB of 4.0 KiB (11 B/s), about 4m 30s left"
+		);
+
+		// Nothing is estimated until there's a rate to go by
+		let status = Status::new(Phase::Measuring { directories: 4 });
+		let (_, message) = status.describe(Duration::from_millis(500));
+		assert_eq!(message, "0 of 4 directories, 0 B");
+	}
+}
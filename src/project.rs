@@ -1,7 +1,94 @@
-use std::path::{Path, PathBuf};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
 
+use globset::GlobMatcher;
+
+use crate::error;
 use crate::swpfile::parse_swpfile;
-use crate::output;
+use crate::utils::glob::{compile_anchored_glob, compile_glob, expand_glob, is_glob};
+
+/// Describes why a directory was marked as cleanable
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+	/// Matched by a built-in rule, because the given marker file exists in
+	/// the project root
+	Marker(String),
+
+	/// Listed in a `.swpfile` (or `.cleanuprc`) on the given line
+	Swpfile { file: String, line: usize },
+
+	/// Requested with the given command line flag, e.g. a global cache
+	Flag(String),
+}
+
+impl Reason {
+	/// Creates a reason for a directory matched by a built-in rule
+	pub fn marker(filename: &str) -> Reason {
+		Reason::Marker(filename.to_owned())
+	}
+}
+
+impl fmt::Display for Reason {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Reason::Marker(marker) => write!(f, "found {}", marker),
+			Reason::Swpfile { file, line } => write!(f, "listed in {} on line {}", file, line),
+			Reason::Flag(flag) => write!(f, "requested with {}", flag),
+		}
+	}
+}
+
+/// How a cleanable directory is cleaned
+#[derive(Debug, Clone, PartialEq)]
+pub enum Strategy {
+	/// Delete the entire directory
+	Remove,
+
+	/// Only delete the given stale files and directories inside it, e.g.
+	/// old artifacts in a Rust target directory
+	Prune(Vec<PathBuf>),
+}
+
+/// A directory inside a project that can be deleted
+#[derive(Debug, Clone)]
+pub struct CleanableDir {
+	/// Absolute path of the directory
+	pub path: PathBuf,
+
+	/// Why this directory was marked as cleanable
+	pub reason: Reason,
+
+	/// Disk space used by the directory in bytes. Will be 0 until the
+	/// directory has been measured.
+	pub size: u64,
+
+	/// Roots of other projects that use the same directory, e.g. a Cargo
+	/// target directory shared by several workspaces
+	pub shared_with: Vec<PathBuf>,
+
+	/// Whether to delete the entire directory or only part of it
+	pub strategy: Strategy,
+}
+
+impl CleanableDir {
+	/// Checks if the directory is a symlink, in which case only the link is
+	/// removed and not the directory it points to
+	pub fn is_symlink(&self) -> bool {
+		fs::symlink_metadata(&self.path)
+			.map(|metadata| metadata.file_type().is_symlink())
+			.unwrap_or(false)
+	}
+
+	/// Gets the paths that will be deleted when cleaning this directory
+	pub fn targets(&self) -> Vec<PathBuf> {
+		match &self.strategy {
+			Strategy::Remove => vec![self.path.clone()],
+			Strategy::Prune(paths) => paths.clone(),
+		}
+	}
+}
 
 /// Describes a discovered cleanable project
 #[derive(Debug)]
@@ -9,11 +96,34 @@ pub struct Project {
 	/// The root directory of the project
 	root: PathBuf,
 
+	/// Names of the ecosystems detected in this project, e.g. `rust`
+	ecosystems: Vec<String>,
+
 	/// Directories containing dependencies
-	dependency_dirs: Vec<PathBuf>,
+	dependency_dirs: Vec<CleanableDir>,
 
-	/// Timestamp indicating when the project was last modified
+	/// Timestamp indicating when the project was last modified, in seconds
+	/// since the Unix epoch. Will be 0 if the modified date was not checked.
 	last_modified: u64,
+
+	/// Overrides the age threshold for this project, if set in its `.swpfile`
+	max_age: Option<Duration>,
+
+	/// Set for projects that are cleaned regardless of when they were last
+	/// modified, like the global Go caches
+	ignore_age: bool,
+
+	/// Patterns for directories that should never be cleaned, listed as
+	/// `!path` in the project's `.swpfile`
+	exclusions: Vec<Exclusion>,
+}
+
+/// A directory or pattern that should never be cleaned
+#[derive(Debug)]
+struct Exclusion {
+	/// The path as listed in the `.swpfile`
+	pattern: String,
+	glob: GlobMatcher,
 }
 
 impl Project {
@@ -24,8 +134,12 @@ impl Project {
 	pub fn new<P: Into<PathBuf>>(root: P) -> Project {
 		Project {
 			root: root.into(),
+			ecosystems: Vec::new(),
 			dependency_dirs: Vec::new(),
 			last_modified: 0,
+			max_age: None,
+			ignore_age: false,
+			exclusions: Vec::new(),
 		}
 	}
 
@@ -33,47 +147,267 @@ impl Project {
 		&self.root
 	}
 
+	/// Gets the names of the ecosystems detected in this project
+	pub fn ecosystems(&self) -> &[String] {
+		&self.ecosystems
+	}
+
+	/// Records that an ecosystem was detected in this project
+	pub fn add_ecosystem(&mut self, name: &str) {
+		if !self.ecosystems.iter().any(|e| e == name) {
+			self.ecosystems.push(name.to_owned());
+		}
+	}
+
+	pub fn last_modified(&self) -> u64 {
+		self.last_modified
+	}
+
+	pub fn set_last_modified(&mut self, timestamp: u64) {
+		self.last_modified = timestamp;
+	}
+
+	/// Gets the age threshold configured in the project's `.swpfile`, if any
+	pub fn max_age(&self) -> Option<Duration> {
+		self.max_age
+	}
+
+	/// Overrides the age threshold for this project
+	pub fn set_max_age(&mut self, max_age: Duration) {
+		self.max_age = Some(max_age);
+	}
+
+	/// Checks if this project is cleaned regardless of when it was last
+	/// modified
+	pub fn ignores_age(&self) -> bool {
+		self.ignore_age
+	}
+
+	/// Cleans this project regardless of when it was last modified
+	pub fn set_ignore_age(&mut self, ignore_age: bool) {
+		self.ignore_age = ignore_age;
+	}
+
 	/// Marks a subdirectory of this project's root directory as cleanable,
-	/// if that directory exists. If the subdirectory doesn't exist, nothing
-	/// happens.
+	/// if that directory exists. If the subdirectory doesn't exist, or isn't
+	/// inside the project root, nothing happens.
 	///
 	/// # Arguments
 	/// `subdir` - Name of the subdirectory inside the project root directory
-	pub fn add_cleanable_dir_if_exists<P: Into<PathBuf>>(&mut self, subdir: P) {
-		let mut path = self.root.clone();
-		path.push(subdir.into());
+	/// `reason` - Why the subdirectory should be cleaned
+	pub fn add_cleanable_dir_if_exists<P: Into<PathBuf>>(&mut self, subdir: P, reason: &Reason) {
+		let path = self.root.join(subdir.into());
+
+		// Absolute paths and `..` would lead outside of the project, and the
+		// root itself is never a subdirectory
+		let inside = match path.strip_prefix(&self.root) {
+			Ok(relative) => {
+				relative.components().next().is_some()
+					&& relative
+						.components()
+						.all(|c| matches!(c, Component::Normal(_)))
+			}
+			Err(_) => false,
+		};
 
-		if path.exists() && path.is_dir() && !self.dependency_dirs.contains(&path) {
-			self.dependency_dirs.push(path);
+		if inside {
+			self.add_external_dir_if_exists(path, reason);
 		}
 	}
 
-	pub fn load_swpfile(&mut self, filename : &str) {
-		let paths = match parse_swpfile(&self.root, &PathBuf::from(filename)) {
-			Ok(paths) => paths,
-			Err(e) => {
-				output::error(format!(
-					"Could not read .swpfile file in {}",
-					self.root.to_str().unwrap_or("")
-				));
-				output::println_info(e.to_string());
-				std::process::exit(1);
+	/// Marks a directory as cleanable if it exists, even if it's outside of
+	/// the project root, like a Cargo target directory configured elsewhere
+	/// or one of the global Go caches
+	///
+	/// # Arguments
+	/// `path`   - Absolute path of the directory
+	/// `reason` - Why the directory should be cleaned
+	pub fn add_external_dir_if_exists<P: Into<PathBuf>>(&mut self, path: P, reason: &Reason) {
+		let path = path.into();
+
+		if path.exists()
+			&& path.is_dir()
+			&& !self.is_cleanable_dir(&path)
+			&& !self.is_excluded(&path)
+			&& !self.contains_excluded(&path)
+			&& !self.leaves_project(&path)
+		{
+			self.dependency_dirs.push(CleanableDir {
+				path,
+				reason: reason.clone(),
+				size: 0,
+				shared_with: Vec::new(),
+				strategy: Strategy::Remove,
+			});
+		}
+	}
+
+	/// Marks all subdirectories of this project's root directory that match
+	/// a glob pattern as cleanable. Patterns without any glob syntax are
+	/// treated the same as `add_cleanable_dir_if_exists`, relative to the
+	/// project root even if they start with `/`.
+	///
+	/// # Arguments
+	/// `pattern` - Gitignore-style pattern, relative to the project root
+	/// `reason`  - Why the matched directories should be cleaned
+	///
+	/// # Returns
+	/// An error message if the pattern is invalid
+	pub fn add_cleanable_glob(&mut self, pattern: &str, reason: &Reason) -> Result<(), String> {
+		if !is_glob(pattern) {
+			self.add_cleanable_dir_if_exists(pattern.trim_start_matches('/'), reason);
+			return Ok(());
+		}
+
+		let glob = compile_glob(pattern)?;
+		let matched = expand_glob(&self.root, &glob, pattern, |path| {
+			self.is_cleanable_dir(path) || self.is_excluded(path)
+		});
+
+		// Matched directories aren't searched any further, so a directory
+		// containing an exclusion is left out entirely
+		let matched: Vec<PathBuf> = matched
+			.into_iter()
+			.filter(|path| !self.contains_excluded(path))
+			.collect();
+
+		for path in matched {
+			self.dependency_dirs.push(CleanableDir {
+				path,
+				reason: reason.clone(),
+				size: 0,
+				shared_with: Vec::new(),
+				strategy: Strategy::Remove,
+			});
+		}
+
+		return Ok(());
+	}
+
+	/// Loads the cleanable directories and settings from a `.swpfile`
+	///
+	/// # Arguments
+	/// `filename` - Name of the file in the project root directory
+	///
+	/// # Returns
+	/// True if the file contains `@defaults`, meaning the built-in rules
+	/// should be applied as well, or an error if the file could not be read
+	/// or contains invalid lines
+	pub fn load_swpfile(&mut self, filename: &str) -> error::Result<bool> {
+		let swpfile = parse_swpfile(&self.root, &PathBuf::from(filename))?;
+
+		self.max_age = swpfile.older_than;
+
+		// Exclusions are loaded first so they apply to every entry, no matter
+		// where they are in the file
+		for entry in swpfile.exclusions {
+			// Like other entries, paths without glob syntax only refer to a
+			// single directory
+			let glob = if is_glob(&entry.path) {
+				compile_glob(&entry.path)
+			} else {
+				compile_anchored_glob(&entry.path)
+			};
+
+			if let Ok(glob) = glob {
+				self.exclusions.push(Exclusion {
+					pattern: entry.path,
+					glob,
+				});
 			}
-		};
+		}
+
+		for entry in swpfile.entries {
+			let reason = Reason::Swpfile {
+				file: filename.to_owned(),
+				line: entry.line,
+			};
+			// Patterns are validated while parsing the file
+			let _ = self.add_cleanable_glob(&entry.path, &reason);
+		}
 
-		for path in paths {
-			self.add_cleanable_dir_if_exists(path);
+		return Ok(swpfile.defaults);
+	}
+
+	/// Checks if the given path, or one of its parent directories inside the
+	/// project, is excluded from cleaning in the project's `.swpfile`
+	pub fn is_excluded<P: AsRef<Path>>(&self, path: P) -> bool {
+		if self.exclusions.is_empty() {
+			return false;
 		}
+
+		let relative = match path.as_ref().strip_prefix(&self.root) {
+			Ok(relative) => relative,
+			Err(_) => return false,
+		};
+
+		return relative
+			.ancestors()
+			.filter(|p| !p.as_os_str().is_empty())
+			.any(|p| self.exclusions.iter().any(|e| e.glob.is_match(p)));
+	}
+
+	/// Checks if an excluded directory exists inside the given directory, in
+	/// which case the directory can't be cleaned without also deleting the
+	/// excluded one
+	fn contains_excluded(&self, path: &Path) -> bool {
+		return self.exclusions.iter().any(|exclusion| {
+			if !is_glob(&exclusion.pattern) {
+				let excluded = self.root.join(exclusion.pattern.trim_start_matches('/'));
+				return excluded != path && excluded.starts_with(path) && excluded.is_dir();
+			}
+
+			// Only the directories leading up to the given one and the ones
+			// inside it need to be searched
+			let matched = expand_glob(&self.root, &exclusion.glob, &exclusion.pattern, |dir| {
+				!dir.starts_with(path) && !path.starts_with(dir)
+			});
+			return matched
+				.iter()
+				.any(|dir| dir != path && dir.starts_with(path));
+		});
+	}
+
+	/// Checks if a path inside the project passes through a symlink, which
+	/// could lead to a directory outside of the project
+	///
+	/// The directory itself may be a symlink, since only the link is removed.
+	fn leaves_project(&self, path: &Path) -> bool {
+		let relative = match path.strip_prefix(&self.root) {
+			Ok(relative) => relative,
+			Err(_) => return false,
+		};
+
+		return relative
+			.ancestors()
+			.skip(1)
+			.filter(|p| !p.as_os_str().is_empty())
+			.any(|p| {
+				fs::symlink_metadata(self.root.join(p))
+					.map(|metadata| metadata.file_type().is_symlink())
+					.unwrap_or(false)
+			});
 	}
 
 	/// Checks if the given path is listed as a cleanable directory of this
 	/// project
-	pub fn is_cleanable_dir<P: Into<PathBuf>>(&self, path: P) -> bool {
-		self.dependency_dirs.contains(&path.into())
+	pub fn is_cleanable_dir<P: AsRef<Path>>(&self, path: P) -> bool {
+		let path = path.as_ref();
+		self.dependency_dirs.iter().any(|dir| dir.path == path)
+	}
+
+	/// Gets the cleanable directories of this project
+	pub fn cleanable_dirs(&self) -> &[CleanableDir] {
+		&self.dependency_dirs
+	}
+
+	/// Removes the cleanable directories for which `keep` returns false
+	pub fn retain_cleanable_dirs<F: FnMut(&CleanableDir) -> bool>(&mut self, keep: F) {
+		self.dependency_dirs.retain(keep);
 	}
 
-	/// Consumes the project and returns the dependency directories
-	pub fn into_cleanable_dirs(self) -> Vec<PathBuf> {
-		self.dependency_dirs
+	/// Gets mutable access to the cleanable directories of this project
+	pub fn cleanable_dirs_mut(&mut self) -> &mut [CleanableDir] {
+		&mut self.dependency_dirs
 	}
 }
@@ -0,0 +1,404 @@
+use std::io::{stdout, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use swp::{Event, Project, Strategy};
+
+use crate::output::Format;
+
+/// Collects machine-readable records when running with `--format json` or
+/// `--format ndjson`
+///
+/// In NDJSON mode, each record is written to stdout as soon as it's added. In
+/// JSON mode, all records are written as a single document when calling
+/// `finish()`. In text mode, records are ignored.
+pub struct Report {
+	format: Format,
+	projects: Vec<ProjectRecord>,
+	deletions: Vec<DeletionRecord>,
+
+	/// True once the JSON document has been written, so it's only written
+	/// once when Ctrl-C is pressed while finishing
+	finished: bool,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum Record<'a> {
+	Project(&'a ProjectRecord),
+	Deletion(&'a DeletionRecord),
+}
+
+/// Whether a discovered project will be cleaned, and if not, why
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectStatus {
+	/// Some of its directories will be cleaned
+	Cleanable,
+
+	/// It was modified more recently than the age threshold
+	Recent,
+
+	/// It could not be read entirely, so it's not cleaned at all
+	Skipped,
+
+	/// All of its cleanable directories are kept, e.g. because they contain
+	/// files tracked by git
+	Kept,
+}
+
+#[derive(Serialize)]
+struct ProjectRecord {
+	root: PathBuf,
+	status: ProjectStatus,
+	ecosystems: Vec<String>,
+	cleanable_dirs: Vec<DirRecord>,
+	/// Unix timestamp, or `null` if the modified date was not checked
+	last_modified: Option<u64>,
+	size: u64,
+	/// Why the project was skipped
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DirRecord {
+	path: PathBuf,
+	reason: String,
+	size: u64,
+	/// Roots of other projects that use the same directory
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	shared_with: Vec<PathBuf>,
+	/// The stale artifacts that will be deleted, if only part of the
+	/// directory is cleaned
+	#[serde(skip_serializing_if = "Option::is_none")]
+	prune: Option<Vec<PathBuf>>,
+}
+
+#[derive(Serialize)]
+struct DeletionRecord {
+	path: PathBuf,
+	success: bool,
+	/// True if the directory was moved to the trash instead of deleted
+	trashed: bool,
+	freed: u64,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	error: Option<String>,
+	/// Files and directories that were left because they belong to another
+	/// user
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	not_owned: Vec<PathBuf>,
+}
+
+#[derive(Serialize)]
+struct Document<'a> {
+	projects: &'a [ProjectRecord],
+	deletions: &'a [DeletionRecord],
+}
+
+impl Report {
+	pub fn new(format: Format) -> Report {
+		Report {
+			format,
+			projects: Vec::new(),
+			deletions: Vec::new(),
+			finished: false,
+		}
+	}
+
+	/// Adds a project that will be cleaned
+	pub fn project(&mut self, project: &Project) {
+		if self.format == Format::Text {
+			return;
+		}
+
+		let cleanable_dirs: Vec<DirRecord> = project
+			.cleanable_dirs()
+			.iter()
+			.map(|dir| DirRecord {
+				path: dir.path.clone(),
+				reason: dir.reason.to_string(),
+				size: dir.size,
+				shared_with: dir.shared_with.clone(),
+				prune: match &dir.strategy {
+					Strategy::Remove => None,
+					Strategy::Prune(paths) => Some(paths.clone()),
+				},
+			})
+			.collect();
+
+		self.add_project(ProjectRecord {
+			root: project.root().to_owned(),
+			status: ProjectStatus::Cleanable,
+			ecosystems: project.ecosystems().to_vec(),
+			size: cleanable_dirs.iter().map(|dir| dir.size).sum(),
+			cleanable_dirs,
+			last_modified: match project.last_modified() {
+				0 => None,
+				timestamp => Some(timestamp),
+			},
+			error: None,
+		});
+	}
+
+	/// Adds a discovered project that won't be cleaned
+	///
+	/// # Arguments
+	/// `root`          - The project's root directory
+	/// `ecosystems`    - The detected ecosystems, if they're known
+	/// `status`        - Why the project won't be cleaned
+	/// `last_modified` - Unix timestamp, or 0 if it wasn't checked
+	/// `error`         - Why the project was skipped, if it was
+	pub fn uncleaned_project(
+		&mut self,
+		root: &Path,
+		ecosystems: &[String],
+		status: ProjectStatus,
+		last_modified: u64,
+		error: Option<String>,
+	) {
+		if self.format == Format::Text {
+			return;
+		}
+
+		self.add_project(ProjectRecord {
+			root: root.to_owned(),
+			status,
+			ecosystems: ecosystems.to_vec(),
+			cleanable_dirs: Vec::new(),
+			last_modified: match last_modified {
+				0 => None,
+				timestamp => Some(timestamp),
+			},
+			size: 0,
+			error,
+		});
+	}
+
+	/// Adds a record for every discovered project to the report, including the
+	/// projects that won't be cleaned
+	///
+	/// # Arguments
+	/// `cleanable`  - The projects that will be cleaned
+	/// `discovered` - The root and ecosystems of each discovered project
+	/// `uncleaned`  - The projects that were skipped or modified recently
+	pub fn discovered_projects(
+		&mut self,
+		cleanable: &[Project],
+		discovered: &[(PathBuf, Vec<String>)],
+		uncleaned: &Uncleaned,
+	) {
+		// Projects skipped while searching were never discovered
+		let mut roots: Vec<&Path> = discovered
+			.iter()
+			.map(|(root, _)| root.as_path())
+			.chain(uncleaned.skipped.iter().map(|(root, _)| root.as_path()))
+			.collect();
+		roots.sort();
+		roots.dedup();
+
+		for root in roots {
+			if let Some(project) = cleanable.iter().find(|p| p.root() == root) {
+				self.project(project);
+				continue;
+			}
+
+			let ecosystems = discovered
+				.iter()
+				.find(|(r, _)| r == root)
+				.map(|(_, ecosystems)| &ecosystems[..])
+				.unwrap_or_default();
+
+			if let Some((_, error)) = uncleaned.skipped.iter().find(|(r, _)| r == root) {
+				let status = ProjectStatus::Skipped;
+				self.uncleaned_project(root, ecosystems, status, 0, Some(error.clone()));
+			} else if let Some((_, modified)) = uncleaned.recent.iter().find(|(r, _)| r == root) {
+				self.uncleaned_project(root, ecosystems, ProjectStatus::Recent, *modified, None);
+			} else {
+				self.uncleaned_project(root, ecosystems, ProjectStatus::Kept, 0, None);
+			}
+		}
+	}
+
+	fn add_project(&mut self, record: ProjectRecord) {
+		if self.format == Format::Ndjson {
+			write_line(&Record::Project(&record));
+		}
+		self.projects.push(record);
+	}
+
+	/// Adds the result of deleting a directory
+	///
+	/// # Arguments
+	/// `path`      - The directory
+	/// `freed`     - Number of bytes freed or moved to the trash
+	/// `trashed`   - True if the directory was moved to the trash
+	/// `error`     - The error that occured while deleting, if any
+	/// `not_owned` - Paths that weren't deleted because they belong to
+	///               another user
+	pub fn deletion(
+		&mut self,
+		path: &Path,
+		freed: u64,
+		trashed: bool,
+		error: Option<String>,
+		not_owned: &[PathBuf],
+	) {
+		if self.format == Format::Text {
+			return;
+		}
+
+		let record = DeletionRecord {
+			path: path.to_owned(),
+			success: error.is_none(),
+			trashed,
+			freed,
+			error,
+			not_owned: not_owned.to_vec(),
+		};
+
+		if self.format == Format::Ndjson {
+			write_line(&Record::Deletion(&record));
+		}
+		self.deletions.push(record);
+	}
+
+	/// Adds a directory that wasn't deleted because Ctrl-C was pressed
+	///
+	/// # Arguments
+	/// `path`    - The directory
+	/// `trashed` - True if the directory would have been moved to the trash
+	pub fn cancelled_deletion(&mut self, path: &Path, trashed: bool) {
+		self.deletion(path, 0, trashed, Some("Cancelled".to_owned()), &[]);
+	}
+
+	/// Writes the JSON document, if running in JSON mode
+	///
+	/// Records added after this are no longer written.
+	pub fn finish(&mut self) {
+		if self.format != Format::Json || self.finished {
+			return;
+		}
+
+		self.finished = true;
+
+		let document = Document {
+			projects: &self.projects,
+			deletions: &self.deletions,
+		};
+
+		write_line(&document);
+	}
+}
+
+/// Projects that won't be cleaned, collected from the scanner's events so
+/// they can be included in the report
+#[derive(Default)]
+pub struct Uncleaned {
+	/// Projects that could not be read entirely, and why
+	skipped: Vec<(PathBuf, String)>,
+
+	/// Projects that were modified recently, and when
+	recent: Vec<(PathBuf, u64)>,
+}
+
+impl Uncleaned {
+	/// Records the project an event is about, if it won't be cleaned
+	pub fn record(&mut self, event: &Event) {
+		match event {
+			Event::ProjectSkipped { root, error } => {
+				self.skipped.push((root.to_path_buf(), error.to_string()))
+			}
+			Event::ProjectRecent(project) => self
+				.recent
+				.push((project.root().to_owned(), project.last_modified())),
+			_ => (),
+		}
+	}
+}
+
+fn write_line<T: Serialize>(value: &T) {
+	let stdout = stdout();
+	let mut stdout = stdout.lock();
+
+	// Serialising these structs can't fail, and there's nothing sensible to
+	// do if stdout is closed
+	let _ = serde_json::to_writer(&mut stdout, value);
+	let _ = writeln!(stdout);
+	let _ = stdout.flush();
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_utils;
+	use swp::Reason;
+
+	#[test]
+	fn project_record() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "target");
+
+			let mut project = Project::new(dir);
+			project.add_ecosystem("rust");
+			project.add_cleanable_dir_if_exists("target", &Reason::marker("Cargo.toml"));
+			project.cleanable_dirs_mut()[0].size = 42;
+
+			let mut report = Report::new(Format::Json);
+			report.project(&project);
+
+			let json = serde_json::to_value(Record::Project(&report.projects[0]))
+				.expect("Could not serialise record");
+
+			assert_eq!(json["type"], "project");
+			assert_eq!(json["status"], "cleanable");
+			assert_eq!(json["ecosystems"][0], "rust");
+			assert_eq!(json["size"], 42);
+			assert_eq!(json["last_modified"], serde_json::Value::Null);
+			assert_eq!(json["cleanable_dirs"][0]["reason"], "found Cargo.toml");
+		});
+	}
+
+	#[test]
+	fn uncleaned_project_record() {
+		let mut report = Report::new(Format::Json);
+		report.uncleaned_project(
+			Path::new("app"),
+			&[],
+			ProjectStatus::Skipped,
+			0,
+			Some("app/.swpfile line 1: Unknown directive".to_owned()),
+		);
+
+		let json = serde_json::to_value(Record::Project(&report.projects[0]))
+			.expect("Could not serialise record");
+
+		assert_eq!(json["status"], "skipped");
+		assert_eq!(json["size"], 0);
+		assert_eq!(json["error"], "app/.swpfile line 1: Unknown directive");
+		assert!(json["cleanable_dirs"].as_array().unwrap().is_empty());
+	}
+
+	#[test]
+	fn cancelled_deletion_record() {
+		let mut report = Report::new(Format::Ndjson);
+		report.cancelled_deletion(Path::new("target"), true);
+
+		let json = serde_json::to_value(Record::Deletion(&report.deletions[0]))
+			.expect("Could not serialise record");
+
+		assert_eq!(json["type"], "deletion");
+		assert_eq!(json["success"], false);
+		assert_eq!(json["trashed"], true);
+		assert_eq!(json["freed"], 0);
+		assert_eq!(json["error"], "Cancelled");
+	}
+
+	#[test]
+	fn text_mode_ignores_records() {
+		let mut report = Report::new(Format::Text);
+		report.deletion(Path::new("target"), 0, false, None, &[]);
+
+		assert!(report.deletions.is_empty());
+	}
+}
@@ -0,0 +1,218 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Component, Path};
+
+use serde::Deserialize;
+
+use crate::config::config_dir;
+use crate::utils::glob::{compile_glob, is_glob};
+
+/// The built-in rules, embedded in the binary
+const DEFAULT_RULES: &str = include_str!("rules.toml");
+
+/// Describes how to recognise a type of project and which of its
+/// directories can be cleaned
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Rule {
+	/// Name of the rule, also used as the name of the project's ecosystem
+	pub name: String,
+
+	/// File names or glob patterns that identify a project
+	pub markers: Vec<String>,
+
+	/// Cleanable directories or glob patterns, relative to the project
+	#[serde(default)]
+	pub dirs: Vec<String>,
+
+	/// Directories in `dirs` that are only cleaned if they contain the given
+	/// file
+	#[serde(default)]
+	pub contains: BTreeMap<String, String>,
+
+	/// Built-in logic for directories that can't be described with a
+	/// pattern
+	#[serde(default)]
+	pub handler: Option<Handler>,
+}
+
+/// Built-in logic to find additional cleanable directories
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Handler {
+	/// The target directory of the Cargo workspace
+	Cargo,
+
+	/// The `bin` directory of a Go module, if it doesn't contain Go sources
+	Go,
+
+	/// The build directories of subprojects included in a Gradle settings file
+	Gradle,
+
+	/// The `bin` and `obj` directories of projects listed in a `.sln` file
+	DotnetSolution,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+	#[serde(default)]
+	rule: Vec<Rule>,
+}
+
+/// Loads the built-in rules, followed by the rules in the user's
+/// `rules.toml` file if it exists
+///
+/// # Returns
+/// All rules in the order they should be checked
+pub fn load() -> Result<Vec<Rule>, String> {
+	let mut rules = default_rules();
+
+	let path = match config_dir() {
+		Some(dir) => dir.join("rules.toml"),
+		None => return Ok(rules),
+	};
+
+	let contents = match fs::read_to_string(&path) {
+		Ok(contents) => contents,
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(rules),
+		Err(e) => return Err(format!("{}: {}", path.to_str().unwrap_or(""), e)),
+	};
+
+	let user_rules =
+		parse_rules(&contents).map_err(|e| format!("{}: {}", path.to_str().unwrap_or(""), e))?;
+	merge_rules(&mut rules, user_rules);
+
+	return Ok(rules);
+}
+
+/// Gets the built-in rules
+pub fn default_rules() -> Vec<Rule> {
+	return parse_rules(DEFAULT_RULES).expect("Invalid built-in rules");
+}
+
+/// Parses and validates the rules in a rules file
+///
+/// # Arguments
+/// `contents` - Contents of the rules file
+///
+/// # Returns
+/// The rules, or a message describing why the file is invalid
+fn parse_rules(contents: &str) -> Result<Vec<Rule>, String> {
+	let file: RulesFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+	for rule in &file.rule {
+		for pattern in rule.markers.iter().chain(&rule.dirs) {
+			if is_glob(pattern) {
+				compile_glob(pattern).map_err(|e| format!("rule {}: {}", rule.name, e))?;
+			}
+		}
+
+		// A leading `/` is relative to the project root, but `..` would lead
+		// outside of the project
+		for dir in &rule.dirs {
+			if Path::new(dir)
+				.components()
+				.any(|c| c == Component::ParentDir)
+			{
+				return Err(format!(
+					"rule {}: {} is outside of the project",
+					rule.name, dir
+				));
+			}
+		}
+
+		for dir in rule.contains.keys() {
+			if !rule.dirs.contains(dir) {
+				return Err(format!("rule {}: {} is not listed in dirs", rule.name, dir));
+			}
+
+			if is_glob(dir) {
+				return Err(format!(
+					"rule {}: contains can't be used with pattern {}",
+					rule.name, dir
+				));
+			}
+		}
+	}
+
+	return Ok(file.rule);
+}
+
+/// Adds user rules to a list of rules
+///
+/// A user rule replaces the existing rule with the same name, so built-in
+/// rules can be changed or disabled. Other rules are added at the end.
+///
+/// # Arguments
+/// `rules`      - The existing rules
+/// `user_rules` - The rules to add
+fn merge_rules(rules: &mut Vec<Rule>, user_rules: Vec<Rule>) {
+	for rule in user_rules {
+		match rules.iter_mut().find(|r| r.name == rule.name) {
+			Some(existing) => *existing = rule,
+			None => rules.push(rule),
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn defaults() {
+		let rules = default_rules();
+		let rust = rules
+			.iter()
+			.find(|r| r.name == "rust")
+			.expect("No rust rule");
+		assert_eq!(rust.markers, vec!["Cargo.toml"]);
+		assert_eq!(rust.handler, Some(Handler::Cargo));
+	}
+
+	#[test]
+	fn merge() {
+		let mut rules = default_rules();
+		let count = rules.len();
+
+		let user_rules = parse_rules(
+			"[[rule]]\nname = \"java\"\nmarkers = []\n\n\
+			 [[rule]]\nname = \"bazel\"\nmarkers = [\"WORKSPACE\", \"MODULE.bazel\"]\ndirs = [\"bazel-*\"]",
+		)
+		.expect("Could not parse rules");
+		merge_rules(&mut rules, user_rules);
+
+		assert_eq!(rules.len(), count + 1);
+		assert!(rules
+			.iter()
+			.find(|r| r.name == "java")
+			.unwrap()
+			.markers
+			.is_empty());
+		assert_eq!(rules[count].name, "bazel");
+		assert_eq!(rules[count].dirs, vec!["bazel-*"]);
+	}
+
+	#[test]
+	fn invalid() {
+		assert!(parse_rules("[[rule]]\nname = \"a\"\nmarkers = [\"a\"]\nfoo = 1").is_err());
+		assert!(parse_rules("[[rule]]\nname = \"a\"\nmarkers = [\"[a\"]").is_err());
+		assert!(
+			parse_rules("[[rule]]\nname = \"a\"\nmarkers = [\"a\"]\nhandler = \"make\"").is_err()
+		);
+		assert!(parse_rules(
+			"[[rule]]\nname = \"a\"\nmarkers = [\"a\"]\ndirs = [\"b\"]\ncontains = { c = \"d\" }"
+		)
+		.is_err());
+		assert!(
+			parse_rules("[[rule]]\nname = \"a\"\nmarkers = [\"a\"]\ndirs = [\"../b\"]").is_err()
+		);
+		assert!(
+			parse_rules("[[rule]]\nname = \"a\"\nmarkers = [\"a\"]\ndirs = [\"b/../../c\"]")
+				.is_err()
+		);
+		assert!(parse_rules("[[rule]]\nname = \"a\"\nmarkers = [\"a\"]\ndirs = [\"/b\"]").is_ok());
+	}
+}
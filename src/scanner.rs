@@ -0,0 +1,284 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crossbeam::queue::SegQueue;
+use regex::Regex;
+
+use crate::analyse_projects::analyse_projects;
+use crate::discover_projects::{discover_projects, module_cache_dirs, CargoEnv};
+use crate::error::Result;
+use crate::rules::{default_rules, Rule};
+use crate::utils::CancellationToken;
+use crate::{Event, Project};
+
+/// Projects modified more recently than this (30 days) are not swept,
+/// unless configured otherwise
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Called for each event while scanning
+type EventHandler = Box<dyn Fn(Event) + Send + Sync>;
+
+/// Finds projects and the directories in them that can be cleaned
+///
+/// A scanner is configured with its builder methods, starting from
+/// `Scanner::new()`. It uses the built-in rules and only cleans projects that
+/// haven't been modified for 30 days, unless configured otherwise.
+///
+/// # Example
+/// ```no_run
+/// use swp::Scanner;
+///
+/// let projects = Scanner::new().path("/home/me/projects").all(true).scan()?;
+/// # Ok::<(), swp::Error>(())
+/// ```
+pub struct Scanner {
+	pub(crate) paths: Vec<PathBuf>,
+	pub(crate) ignore: Option<Regex>,
+	pub(crate) older_than: Duration,
+	pub(crate) all: bool,
+	pub(crate) rules: Vec<Rule>,
+	pub(crate) prune: bool,
+	pub(crate) go_cache: bool,
+	pub(crate) follow_symlinks: bool,
+	pub(crate) one_file_system: bool,
+	pub(crate) cargo: CargoEnv,
+
+	/// Directories that are never searched, because they're the Go module
+	/// cache full of read-only modules
+	pub(crate) go_module_caches: Vec<PathBuf>,
+	pub(crate) cancel: CancellationToken,
+	on_event: Option<EventHandler>,
+}
+
+impl Default for Scanner {
+	fn default() -> Scanner {
+		Scanner::new()
+	}
+}
+
+impl Scanner {
+	pub fn new() -> Scanner {
+		Scanner {
+			paths: Vec::new(),
+			ignore: None,
+			older_than: DEFAULT_MAX_AGE,
+			all: false,
+			rules: default_rules(),
+			prune: false,
+			go_cache: false,
+			follow_symlinks: false,
+			one_file_system: false,
+			cargo: CargoEnv::from_env(),
+			go_module_caches: module_cache_dirs(|var| env::var_os(var)),
+			cancel: CancellationToken::new(),
+			on_event: None,
+		}
+	}
+
+	/// Adds a directory to search for projects
+	///
+	/// Paths should be absolute, since the discovered projects and their
+	/// directories are relative to the path they were found in.
+	pub fn path<P: Into<PathBuf>>(mut self, path: P) -> Scanner {
+		self.paths.push(path.into());
+		self
+	}
+
+	/// Adds several directories to search for projects
+	pub fn paths<I: IntoIterator<Item = P>, P: Into<PathBuf>>(mut self, paths: I) -> Scanner {
+		self.paths.extend(paths.into_iter().map(Into::into));
+		self
+	}
+
+	/// Excludes projects in directories matched by a regex pattern
+	pub fn ignore(mut self, ignore: Regex) -> Scanner {
+		self.ignore = Some(ignore);
+		self
+	}
+
+	/// Only cleans projects that haven't been modified for this long, unless
+	/// overridden by the project's `.swpfile`
+	pub fn older_than(mut self, older_than: Duration) -> Scanner {
+		self.older_than = older_than;
+		self
+	}
+
+	/// Cleans projects regardless of when they were last modified, and also
+	/// cleans Rust target directories outside of their workspace (set with
+	/// `CARGO_TARGET_DIR` or `build.target-dir`), which other projects may
+	/// use as well
+	pub fn all(mut self, all: bool) -> Scanner {
+		self.all = all;
+		self
+	}
+
+	/// Sets the rules used to detect projects, replacing the built-in rules
+	///
+	/// Use `rules::load()` to get the built-in rules combined with the user's
+	/// `rules.toml` file.
+	pub fn rules(mut self, rules: Vec<Rule>) -> Scanner {
+		self.rules = rules;
+		self
+	}
+
+	/// Only cleans stale build artifacts in Rust target directories, instead
+	/// of the entire directory, even in recently modified projects
+	pub fn prune(mut self, prune: bool) -> Scanner {
+		self.prune = prune;
+		self
+	}
+
+	/// Also cleans the global Go build cache and module cache
+	pub fn go_cache(mut self, go_cache: bool) -> Scanner {
+		self.go_cache = go_cache;
+		self
+	}
+
+	/// Also searches directories that are symlinks
+	pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Scanner {
+		self.follow_symlinks = follow_symlinks;
+		self
+	}
+
+	/// Doesn't search or clean directories on other filesystems than the one
+	/// each path is on
+	pub fn one_file_system(mut self, one_file_system: bool) -> Scanner {
+		self.one_file_system = one_file_system;
+		self
+	}
+
+	/// Sets the Cargo environment variables used to find the target
+	/// directories of Rust projects, instead of reading them from the
+	/// environment of the current process
+	pub fn cargo_env(mut self, cargo: CargoEnv) -> Scanner {
+		self.cargo = cargo;
+		self
+	}
+
+	/// Sets the token that stops scanning when cancelled
+	///
+	/// A cancelled scan returns the results it has so far, which may be
+	/// incomplete.
+	pub fn cancel_token(mut self, cancel: CancellationToken) -> Scanner {
+		self.cancel = cancel;
+		self
+	}
+
+	/// Sets the function that is called for each event
+	///
+	/// It's called from several threads, so it should return quickly.
+	pub fn on_event<F: Fn(Event) + Send + Sync + 'static>(mut self, on_event: F) -> Scanner {
+		self.on_event = Some(Box::new(on_event));
+		self
+	}
+
+	/// Finds all projects and the directories in them that can be cleaned
+	///
+	/// This is the same as calling `discover()` followed by `analyse()`.
+	///
+	/// # Returns
+	/// The projects that can be cleaned, sorted by their root directory
+	pub fn scan(&self) -> Result<Vec<Project>> {
+		let projects = self.discover()?;
+		return self.analyse(projects);
+	}
+
+	/// Searches the paths for projects
+	///
+	/// Each project is also reported with an `Event::Found` as soon as it's
+	/// found. Directories that can't be searched and projects that can't be
+	/// detected are reported as events and skipped.
+	///
+	/// # Returns
+	/// All discovered projects in no particular order, with their cleanable
+	/// directories not filtered or measured yet, or an error if searching
+	/// stopped unexpectedly
+	pub fn discover(&self) -> Result<Vec<Project>> {
+		let discovered = discover_projects(self)?;
+
+		let mut projects = Vec::with_capacity(discovered.len());
+		while let Ok(project) = discovered.pop() {
+			projects.push(project);
+		}
+
+		return Ok(projects);
+	}
+
+	/// Decides which of the discovered projects and directories can be
+	/// cleaned, and measures the directories
+	///
+	/// Projects that can't be checked entirely are reported with an
+	/// `Event::ProjectSkipped` and left out, since they may have been
+	/// modified recently.
+	///
+	/// # Arguments
+	/// `projects` - The projects returned by `discover()`
+	///
+	/// # Returns
+	/// The projects that can be cleaned, sorted by their root directory, or
+	/// an error if analysing stopped unexpectedly
+	pub fn analyse(&self, projects: Vec<Project>) -> Result<Vec<Project>> {
+		let queue = SegQueue::new();
+		for project in projects {
+			queue.push(project);
+		}
+
+		return analyse_projects(queue, self);
+	}
+
+	/// Checks if a path matches the ignore pattern, or is in the Go module
+	/// cache
+	///
+	/// # Returns
+	/// True if the path is ignored, false if no ignore pattern was set or if
+	/// the path is empty
+	pub(crate) fn is_path_ignored(&self, path: &Path) -> bool {
+		if self
+			.go_module_caches
+			.iter()
+			.any(|dir| path.starts_with(dir))
+		{
+			return true;
+		}
+
+		let re = match &self.ignore {
+			Some(re) => re,
+			None => return false,
+		};
+
+		let path = path.to_str().unwrap_or("");
+
+		if path.is_empty() {
+			return false;
+		} else {
+			return re.is_match(path);
+		}
+	}
+
+	/// Reports an event to the callback, if one was set
+	pub(crate) fn emit(&self, event: Event) {
+		if let Some(on_event) = &self.on_event {
+			on_event(event);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn ignore() {
+		let scanner = Scanner::new().ignore(Regex::new("src").unwrap());
+
+		assert!(scanner.is_path_ignored(Path::new("./src")));
+		assert!(!scanner.is_path_ignored(Path::new("./foo")));
+		assert!(!Scanner::new().is_path_ignored(Path::new("./src")));
+
+		let mut scanner = Scanner::new();
+		scanner.go_module_caches = vec![PathBuf::from("/home/me/go/pkg/mod")];
+		assert!(scanner.is_path_ignored(Path::new("/home/me/go/pkg/mod/golang.org/x/text@v0.3.0")));
+		assert!(!scanner.is_path_ignored(Path::new("/home/me/go/src")));
+	}
+}
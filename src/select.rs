@@ -0,0 +1,472 @@
+use std::io::{stderr, stdin, stdout, Write};
+use std::path::PathBuf;
+
+use yansi::Paint;
+
+use swp::duration::format_age;
+use swp::Project;
+
+use crate::output;
+
+const HELP: &str = "\
+Commands:
+  3        Toggle all directories of project 3
+  3.2      Toggle the second directory of project 3
+  1-4      Toggle projects 1 to 4
+  all      Select all visible directories
+  none     Deselect all visible directories
+  /text    Only show projects and directories containing `text`
+  /        Clear the filter
+  done     Delete the selected directories
+  quit     Cancel without deleting anything
+Several commands can be combined on one line, e.g. `1 3.2 5-7`";
+
+/// What should happen after a command has been handled
+#[derive(Debug, PartialEq)]
+enum Action {
+	/// Show the list again and wait for the next command
+	Continue,
+
+	/// Show the available commands
+	Help,
+
+	/// Delete the selected directories
+	Done,
+
+	/// Cancel without deleting anything
+	Quit,
+}
+
+/// The state of the interactive checklist
+struct Selection<'a> {
+	projects: &'a [Project],
+
+	/// Whether each directory is selected, indexed by project and directory
+	selected: Vec<Vec<bool>>,
+
+	/// Only projects and directories containing this text are shown
+	filter: Option<String>,
+}
+
+impl<'a> Selection<'a> {
+	/// Creates a selection with all directories selected
+	fn new(projects: &'a [Project]) -> Selection<'a> {
+		Selection {
+			projects,
+			selected: projects
+				.iter()
+				.map(|p| vec![true; p.cleanable_dirs().len()])
+				.collect(),
+			filter: None,
+		}
+	}
+
+	/// Checks if a directory is visible with the current filter
+	fn is_visible(&self, project: usize, dir: usize) -> bool {
+		let filter = match &self.filter {
+			Some(filter) => filter,
+			None => return true,
+		};
+
+		let project = &self.projects[project];
+		let matches = |path: &std::path::Path| path.to_string_lossy().contains(filter.as_str());
+
+		matches(project.root()) || matches(&project.cleanable_dirs()[dir].path)
+	}
+
+	/// Toggles all visible directories of a project. If any of them are
+	/// selected they are all deselected, otherwise they are all selected.
+	fn toggle_project(&mut self, project: usize) {
+		let visible: Vec<usize> = (0..self.selected[project].len())
+			.filter(|&dir| self.is_visible(project, dir))
+			.collect();
+
+		let select = !visible.iter().any(|&dir| self.selected[project][dir]);
+		for dir in visible {
+			self.selected[project][dir] = select;
+		}
+	}
+
+	/// Selects or deselects all visible directories
+	fn set_all(&mut self, select: bool) {
+		for project in 0..self.selected.len() {
+			for dir in 0..self.selected[project].len() {
+				if self.is_visible(project, dir) {
+					self.selected[project][dir] = select;
+				}
+			}
+		}
+	}
+
+	/// Handles a line of user input
+	///
+	/// # Returns
+	/// The action to take, or a message describing why the input is invalid
+	fn handle(&mut self, input: &str) -> Result<Action, String> {
+		let input = input.trim();
+
+		// Filters can contain spaces, so they take up the entire line
+		if let Some(filter) = input.strip_prefix('/') {
+			let filter = filter.trim();
+			self.filter = if filter.is_empty() {
+				None
+			} else {
+				Some(filter.to_owned())
+			};
+			return Ok(Action::Continue);
+		}
+
+		// Validate the entire line before changing anything, so a typo
+		// doesn't leave the selection half updated
+		let mut commands = Vec::new();
+		for word in input.split_whitespace() {
+			commands.push(self.parse_command(word)?);
+		}
+
+		for command in commands {
+			match command {
+				Command::Action(action) => return Ok(action),
+				Command::All => self.set_all(true),
+				Command::None => self.set_all(false),
+				Command::Project(project) => self.toggle_project(project),
+				Command::Range(from, to) => {
+					for project in from.min(to)..=from.max(to) {
+						self.toggle_project(project);
+					}
+				}
+				Command::Dir(project, dir) => {
+					self.selected[project][dir] = !self.selected[project][dir]
+				}
+			}
+		}
+
+		return Ok(Action::Continue);
+	}
+
+	/// Parses a single command
+	fn parse_command(&self, word: &str) -> Result<Command, String> {
+		match word.to_lowercase().as_str() {
+			"a" | "all" => return Ok(Command::All),
+			"n" | "none" => return Ok(Command::None),
+			"d" | "done" | "y" | "yes" => return Ok(Command::Action(Action::Done)),
+			"q" | "quit" => return Ok(Command::Action(Action::Quit)),
+			"h" | "help" | "?" => return Ok(Command::Action(Action::Help)),
+			_ => (),
+		}
+
+		let invalid = || format!("Unknown command '{}', type `help` for help", word);
+
+		if let Some(index) = word.find('.') {
+			let project = self.parse_project(&word[..index]).ok_or_else(invalid)?;
+			let dir = word[index + 1..]
+				.parse::<usize>()
+				.ok()
+				.filter(|&dir| dir >= 1 && dir <= self.selected[project].len())
+				.ok_or_else(invalid)?;
+
+			return Ok(Command::Dir(project, dir - 1));
+		}
+
+		if let Some(index) = word.find('-') {
+			let from = self.parse_project(&word[..index]).ok_or_else(invalid)?;
+			let to = self.parse_project(&word[index + 1..]).ok_or_else(invalid)?;
+
+			return Ok(Command::Range(from, to));
+		}
+
+		let project = self.parse_project(word).ok_or_else(invalid)?;
+		return Ok(Command::Project(project));
+	}
+
+	/// Parses a 1-based project number into an index
+	fn parse_project(&self, number: &str) -> Option<usize> {
+		number
+			.parse::<usize>()
+			.ok()
+			.filter(|&project| project >= 1 && project <= self.projects.len())
+			.map(|project| project - 1)
+	}
+
+	/// Gets the paths of all selected directories
+	fn selected_paths(&self) -> Vec<PathBuf> {
+		let mut paths = Vec::new();
+
+		for (project, selected) in self.projects.iter().zip(&self.selected) {
+			for (dir, &is_selected) in project.cleanable_dirs().iter().zip(selected) {
+				if is_selected {
+					paths.push(dir.path.clone());
+				}
+			}
+		}
+
+		return paths;
+	}
+
+	/// Renders the checklist
+	fn render(&self) -> String {
+		let mut lines = Vec::new();
+
+		for (p, project) in self.projects.iter().enumerate() {
+			let visible: Vec<usize> = (0..project.cleanable_dirs().len())
+				.filter(|&dir| self.is_visible(p, dir))
+				.collect();
+
+			if visible.is_empty() {
+				continue;
+			}
+
+			let size: u64 = project.cleanable_dirs().iter().map(|d| d.size).sum();
+			lines.push(format!(
+				"{} {} {}  {}",
+				Paint::new(format!("{:>4}", format!("{}.", p + 1))).bold(),
+				checkbox(
+					self.selected[p].iter().all(|&s| s),
+					self.selected[p].iter().any(|&s| s)
+				),
+				Paint::new(project.root().to_string_lossy()).bold(),
+				Paint::new(format!(
+					"{}{}",
+					output::format_size(size),
+					describe_age(project)
+				))
+				.dimmed(),
+			));
+
+			for dir in visible {
+				let cleanable = &project.cleanable_dirs()[dir];
+				lines.push(format!(
+					"{:>8} {} {:>10}  {}",
+					format!("{}.{}", p + 1, dir + 1),
+					checkbox(self.selected[p][dir], self.selected[p][dir]),
+					output::format_size(cleanable.size),
+					cleanable.path.to_string_lossy(),
+				));
+			}
+		}
+
+		if lines.is_empty() {
+			lines.push("No directories match the filter".to_owned());
+		}
+
+		let selected = self.selected_paths();
+		let selected_size: u64 = self
+			.projects
+			.iter()
+			.flat_map(|p| p.cleanable_dirs())
+			.filter(|d| selected.contains(&d.path))
+			.map(|d| d.size)
+			.sum();
+
+		lines.push(String::new());
+		lines.push(format!(
+			"{} {} selected, {}{}",
+			selected.len(),
+			if selected.len() == 1 {
+				"directory"
+			} else {
+				"directories"
+			},
+			output::format_size(selected_size),
+			match &self.filter {
+				Some(filter) => format!(" (filter: {})", filter),
+				None => String::new(),
+			}
+		));
+
+		return lines.join("\n");
+	}
+}
+
+/// A single parsed command
+#[derive(Debug, PartialEq)]
+enum Command {
+	Action(Action),
+	All,
+	None,
+	Project(usize),
+	Range(usize, usize),
+	Dir(usize, usize),
+}
+
+fn checkbox(all: bool, any: bool) -> &'static str {
+	if all {
+		"[x]"
+	} else if any {
+		"[-]"
+	} else {
+		"[ ]"
+	}
+}
+
+fn describe_age(project: &Project) -> String {
+	if project.last_modified() == 0 {
+		return String::new();
+	}
+
+	format!(", modified {}", format_age(project.last_modified()))
+}
+
+/// Shows an interactive checklist of all cleanable directories, grouped by
+/// project, so the user can choose which ones should be deleted
+///
+/// The checklist is written to stdout, or to stderr when writing structured
+/// output so stdout only contains the records.
+///
+/// # Arguments
+/// `projects` - The projects to choose from
+///
+/// # Returns
+/// The selected directories, or None if the user cancelled
+pub fn select_dirs(projects: &[Project]) -> Option<Vec<PathBuf>> {
+	let mut selection = Selection::new(projects);
+	let mut out: Box<dyn Write> = if output::is_structured() {
+		Box::new(stderr())
+	} else {
+		Box::new(stdout())
+	};
+
+	let _ = writeln!(out, "\n{}\n", selection.render());
+
+	loop {
+		let _ = write!(
+			out,
+			"{} ",
+			Paint::new("Select directories (`help` for commands, `done` to continue):").bold()
+		);
+		let _ = out.flush();
+
+		let mut input = String::new();
+		match stdin().read_line(&mut input) {
+			// End of input, e.g. when stdin is closed
+			Ok(0) | Err(_) => return None,
+			Ok(_) => (),
+		}
+
+		match selection.handle(&input) {
+			Ok(Action::Continue) => {
+				let _ = writeln!(out, "\n{}\n", selection.render());
+			}
+			Ok(Action::Help) => {
+				let _ = writeln!(out, "{}", HELP);
+			}
+			Ok(Action::Done) => return Some(selection.selected_paths()),
+			Ok(Action::Quit) => return None,
+			Err(message) => {
+				let _ = writeln!(out, "{}", Paint::red(message));
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::test_utils;
+	use swp::Reason;
+
+	/// Creates two projects with two cleanable directories each and runs the
+	/// given test with them
+	fn with_projects<F: FnOnce(&[Project])>(test: F) {
+		test_utils::with_temp_dir(|dir| {
+			let mut projects = Vec::new();
+
+			for name in ["frontend", "backend"].iter() {
+				test_utils::create_dir(dir, name);
+				test_utils::create_dir(dir, &format!("{}/node_modules", name));
+				test_utils::create_dir(dir, &format!("{}/build", name));
+
+				let mut project = Project::new(dir.join(name));
+				let reason = Reason::marker("package.json");
+				project.add_cleanable_dir_if_exists("node_modules", &reason);
+				project.add_cleanable_dir_if_exists("build", &reason);
+				projects.push(project);
+			}
+
+			test(&projects);
+		});
+	}
+
+	#[test]
+	fn all_selected_by_default() {
+		with_projects(|projects| {
+			let selection = Selection::new(projects);
+			assert_eq!(selection.selected_paths().len(), 4);
+		});
+	}
+
+	#[test]
+	fn toggle() {
+		with_projects(|projects| {
+			let mut selection = Selection::new(projects);
+
+			assert_eq!(selection.handle("1"), Ok(Action::Continue));
+			assert_eq!(selection.selected_paths().len(), 2);
+
+			assert_eq!(selection.handle("2.1 1.2"), Ok(Action::Continue));
+			assert_eq!(
+				selection.selected_paths(),
+				vec![
+					projects[0].cleanable_dirs()[1].path.clone(),
+					projects[1].cleanable_dirs()[1].path.clone()
+				]
+			);
+
+			// A partially selected project is deselected entirely
+			assert_eq!(selection.handle("2"), Ok(Action::Continue));
+			assert_eq!(selection.selected_paths().len(), 1);
+
+			assert_eq!(selection.handle("none"), Ok(Action::Continue));
+			assert!(selection.selected_paths().is_empty());
+
+			assert_eq!(selection.handle("1-2"), Ok(Action::Continue));
+			assert_eq!(selection.selected_paths().len(), 4);
+		});
+	}
+
+	#[test]
+	fn filter() {
+		with_projects(|projects| {
+			let mut selection = Selection::new(projects);
+
+			assert_eq!(selection.handle("/backend"), Ok(Action::Continue));
+			assert_eq!(selection.handle("none"), Ok(Action::Continue));
+			assert_eq!(
+				selection.selected_paths(),
+				vec![
+					projects[0].cleanable_dirs()[0].path.clone(),
+					projects[0].cleanable_dirs()[1].path.clone()
+				]
+			);
+
+			assert_eq!(selection.handle("/"), Ok(Action::Continue));
+			assert_eq!(selection.handle("none"), Ok(Action::Continue));
+			assert!(selection.selected_paths().is_empty());
+		});
+	}
+
+	#[test]
+	fn invalid_input() {
+		with_projects(|projects| {
+			let mut selection = Selection::new(projects);
+
+			assert!(selection.handle("3").is_err());
+			assert!(selection.handle("1.3").is_err());
+			assert!(selection.handle("foo").is_err());
+
+			// Nothing is changed if any command on the line is invalid
+			assert!(selection.handle("1 foo").is_err());
+			assert_eq!(selection.selected_paths().len(), 4);
+		});
+	}
+
+	#[test]
+	fn actions() {
+		with_projects(|projects| {
+			let mut selection = Selection::new(projects);
+
+			assert_eq!(selection.handle("done"), Ok(Action::Done));
+			assert_eq!(selection.handle("q"), Ok(Action::Quit));
+			assert_eq!(selection.handle("help"), Ok(Action::Help));
+		});
+	}
+}
@@ -1,18 +1,29 @@
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
+use std::time::Duration;
 
 use dunce::canonicalize;
 use regex::Regex;
 use structopt::StructOpt;
+use swp::config::Config;
+use swp::duration::parse_duration;
+use swp::rules::{self, Rule};
+use swp::{CancellationToken, Cleaner, Scanner, DEFAULT_MAX_AGE};
+
+use crate::output::Format;
 
 pub enum SettingsError {
 	InvalidPath(PathBuf),
+	InvalidConfig(String),
+
+	/// An option that isn't available on this platform
+	Unsupported(&'static str),
 }
 
 pub type Result<T> = std::result::Result<T, SettingsError>;
 
 /// Deletes unnecessary build artifacts and dependency directories in your projects.
 ///
-/// Detects Rust, Java and NodeJS projects by default, or define your own cleanable directories by adding a `.cleanuprc` file to your project directory.
+/// Detects Rust, Go, Java, Gradle, .NET, Python and NodeJS projects by default, or define your own cleanable directories by adding a `.cleanuprc` file to your project directory.
 ///
 /// Questions, bugs & other issues: https://github.com/woubuc/sweep/issues
 #[derive(Debug, StructOpt)]
@@ -22,10 +33,15 @@ pub struct Settings {
 	#[structopt(name = "PATH...")]
 	pub paths: Vec<PathBuf>,
 
-	/// Sweep even projects that were modified within the last 30 days.
+	/// Sweep even projects that were modified recently.
 	#[structopt(short = "a", long = "all")]
 	pub all: bool,
 
+	/// Only sweep projects that haven't been modified for this long, e.g. `7d`, `2w` or `6months`.
+	/// Defaults to 30 days.
+	#[structopt(short = "o", long = "older-than", parse(try_from_str = parse_duration))]
+	pub older_than: Option<Duration>,
+
 	/// Exclude projects in directories matched by this regex pattern.
 	#[structopt(short = "i", long = "ignore")]
 	pub ignore: Option<Regex>,
@@ -33,23 +49,91 @@ pub struct Settings {
 	/// Skip confirmation prompt before removing directories. Use at your own risk.
 	#[structopt(short = "f", long = "force")]
 	pub force: bool,
+
+	/// List the directories that would be deleted and why, without deleting anything.
+	/// Exits with status code 2 if any directories were found.
+	#[structopt(short = "n", long = "dry-run")]
+	pub dry_run: bool,
+
+	/// Choose which directories to delete from a checklist, instead of confirming all of them at once.
+	#[structopt(long = "interactive")]
+	pub interactive: bool,
+
+	/// Move directories to the trash instead of deleting them permanently.
+	#[structopt(short = "t", long = "trash")]
+	pub trash: bool,
+
+	/// For Rust projects, only delete stale build artifacts instead of the entire target directory,
+	/// so recent builds stay warm. Applies to recently modified projects as well.
+	#[structopt(long = "prune")]
+	pub prune: bool,
+
+	/// Also delete the global Go build cache and module cache. Go downloads and rebuilds
+	/// them as needed.
+	#[structopt(long = "go-cache")]
+	pub go_cache: bool,
+
+	/// Also search directories that are symlinks. Symlinks that lead back to a directory
+	/// that was already searched are skipped.
+	#[structopt(long = "follow-symlinks")]
+	pub follow_symlinks: bool,
+
+	/// Don't search or delete directories on other filesystems than the one each path is on.
+	#[structopt(short = "x", long = "one-file-system")]
+	pub one_file_system: bool,
+
+	/// Output format: `text` for the terminal, or `json` / `ndjson` for machine-readable records on stdout.
+	#[structopt(long = "format", default_value = "text", possible_values = &["text", "json", "ndjson"])]
+	pub format: Format,
+
+	/// Rules used to detect projects, loaded from the built-in rules and the
+	/// user's `rules.toml` file
+	#[structopt(skip)]
+	pub rules: Vec<Rule>,
 }
 
 impl Settings {
 	/// Gets a Settings struct from the CLI arguments
-	pub fn get() -> Result<Settings> {
-		let mut settings: Settings = Settings::from_args();
+	///
+	/// The config file and rules aren't loaded yet, so the output format can
+	/// be set before reporting errors in them. Call `load()` next.
+	pub fn get() -> Settings {
+		return Settings::from_args();
+	}
 
-		settings.validate()?;
+	/// Loads the user config file and rules, and validates the settings
+	pub fn load(&mut self) -> Result<()> {
+		// Values from the user config file are only used when they weren't
+		// passed on the command line
+		let config = Config::load().map_err(SettingsError::InvalidConfig)?;
+		if self.older_than.is_none() {
+			if let Some(older_than) = config.older_than {
+				self.older_than = Some(
+					parse_duration(&older_than)
+						.map_err(|e| SettingsError::InvalidConfig(format!("older-than: {}", e)))?,
+				);
+			}
+		}
 
-		Ok(settings)
+		self.rules = rules::load().map_err(SettingsError::InvalidConfig)?;
+
+		return self.validate();
 	}
 
 	/// Validates the application-specific values in a settings struct.
 	///
-	/// This method is called automatically when calling `.get()`, but it
-	/// should be called manually when creating a custom settings object.
+	/// This method is called automatically by `.load()`, after the config
+	/// file and rules are loaded. It should be called manually when creating
+	/// a custom settings object.
 	pub fn validate(&mut self) -> Result<()> {
+		// Checked before scanning, so the user isn't asked to confirm
+		// moving directories to a trash that doesn't exist
+		if self.trash && !Cleaner::can_trash() {
+			return Err(SettingsError::Unsupported(
+				"Moving to the trash is not supported on this platform",
+			));
+		}
+
 		// If no paths are set, add the current path
 		if self.paths.is_empty() {
 			self.paths.push(".".into());
@@ -69,29 +153,33 @@ impl Settings {
 		Ok(())
 	}
 
-	/// Checks if a given path is ignored
+	/// Gets the minimum time since a project was last modified before it can
+	/// be swept, unless overridden by the project's `.swpfile`
+	pub fn max_age(&self) -> Duration {
+		self.older_than.unwrap_or(DEFAULT_MAX_AGE)
+	}
+
+	/// Creates a scanner that searches the given paths with these settings
 	///
 	/// # Arguments
-	/// * `ignore` - The ignore regex, if set
-	/// * `path`   - Path to check against the ignore regex
-	///
-	/// # Returns
-	/// * `true`  - If the path matches the regex
-	/// * `false` - If the regex and path don't match, if no ignore
-	///             regex was given, or if the path is empty
-	pub fn is_path_ignored(&self, path: &Path) -> bool {
-		if self.ignore.is_none() {
-			return false;
-		}
+	/// `cancel` - Stops scanning when cancelled
+	pub fn scanner(&self, cancel: &CancellationToken) -> Scanner {
+		let mut scanner = Scanner::new()
+			.paths(&self.paths)
+			.older_than(self.max_age())
+			.all(self.all)
+			.rules(self.rules.clone())
+			.prune(self.prune)
+			.go_cache(self.go_cache)
+			.follow_symlinks(self.follow_symlinks)
+			.one_file_system(self.one_file_system)
+			.cancel_token(cancel.clone());
 
-		let re = self.ignore.as_ref().unwrap();
-		let path = path.to_str().unwrap_or("");
-
-		if path.len() == 0 {
-			return false;
-		} else {
-			return re.is_match(path);
+		if let Some(ignore) = &self.ignore {
+			scanner = scanner.ignore(ignore.clone());
 		}
+
+		return scanner;
 	}
 }
 
@@ -104,15 +192,25 @@ mod tests {
 		let mut settings = Settings {
 			paths: vec![],
 			all: false,
+			older_than: None,
 			ignore: None,
 			force: false,
+			dry_run: false,
+			interactive: false,
+			trash: false,
+			prune: false,
+			go_cache: false,
+			follow_symlinks: false,
+			one_file_system: false,
+			format: Format::Text,
+			rules: Vec::new(),
 		};
 
 		assert!(
 			settings.validate().is_ok(),
 			"An error occured while validating settings struct"
 		);
-		assert!(settings.paths.len() > 0, "Settings contains no paths");
+		assert!(!settings.paths.is_empty(), "Settings contains no paths");
 	}
 
 	#[test]
@@ -120,8 +218,18 @@ mod tests {
 		let mut settings = Settings {
 			paths: vec!["./this_path_does_not_exist_1".into()],
 			all: false,
+			older_than: None,
 			ignore: None,
 			force: false,
+			dry_run: false,
+			interactive: false,
+			trash: false,
+			prune: false,
+			go_cache: false,
+			follow_symlinks: false,
+			one_file_system: false,
+			format: Format::Text,
+			rules: Vec::new(),
 		};
 
 		let validate = settings.validate();
@@ -130,22 +238,59 @@ mod tests {
 			"No error occured while validating invalid settings struct"
 		);
 
-		match validate.unwrap_err() {
-			SettingsError::InvalidPath(_) => (),
-			_ => panic!("Unexpected error returned"),
+		assert!(
+			matches!(validate.unwrap_err(), SettingsError::InvalidPath(_)),
+			"Unexpected error returned"
+		);
+	}
+
+	#[test]
+	fn trash() {
+		let mut settings = Settings {
+			paths: vec![],
+			all: false,
+			older_than: None,
+			ignore: None,
+			force: false,
+			dry_run: false,
+			interactive: false,
+			trash: true,
+			prune: false,
+			go_cache: false,
+			follow_symlinks: false,
+			one_file_system: false,
+			format: Format::Text,
+			rules: Vec::new(),
+		};
+
+		match settings.validate() {
+			Ok(()) => assert!(Cleaner::can_trash()),
+			Err(SettingsError::Unsupported(_)) => assert!(!Cleaner::can_trash()),
+			Err(_) => panic!("Unexpected error returned"),
 		}
 	}
 
 	#[test]
-	fn ignore_flag() {
-		let settings = Settings {
+	fn max_age() {
+		let mut settings = Settings {
 			paths: vec![],
 			all: false,
-			ignore: Some(Regex::new("src").unwrap()),
+			older_than: None,
+			ignore: None,
 			force: false,
+			dry_run: false,
+			interactive: false,
+			trash: false,
+			prune: false,
+			go_cache: false,
+			follow_symlinks: false,
+			one_file_system: false,
+			format: Format::Text,
+			rules: Vec::new(),
 		};
+		assert_eq!(settings.max_age(), DEFAULT_MAX_AGE);
 
-		assert_eq!(settings.is_path_ignored(Path::new("./src")), true);
-		assert_eq!(settings.is_path_ignored(Path::new("./foo")), false);
+		settings.older_than = Some(Duration::from_secs(3600));
+		assert_eq!(settings.max_age(), Duration::from_secs(3600));
 	}
 }
@@ -1,30 +1,109 @@
 use std::fs::File;
-use std::io::{BufRead, BufReader, Result};
+use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::time::Duration;
 
-pub fn parse_swpfile<P: AsRef<Path>>(dir: P, filename : P) -> Result<Vec<String>> {
-	let file = File::open(dir.as_ref().join(filename))?;
+use crate::error::{Error, Result};
+use crate::utils::duration::parse_duration;
+use crate::utils::glob::{compile_glob, is_glob};
+
+/// The parsed contents of a `.swpfile`
+#[derive(Debug, Default)]
+pub struct Swpfile {
+	/// The listed directories
+	pub entries: Vec<SwpfileEntry>,
+
+	/// Overrides the age threshold for this project, set with an
+	/// `@older-than <duration>` line
+	pub older_than: Option<Duration>,
+
+	/// Directories that should never be cleaned, listed as `!path`
+	pub exclusions: Vec<SwpfileEntry>,
+
+	/// True if the built-in rules should be applied as well as the listed
+	/// directories, set with an `@defaults` line
+	pub defaults: bool,
+}
+
+/// A single directory or pattern listed in a `.swpfile`
+#[derive(Debug)]
+pub struct SwpfileEntry {
+	/// The line number in the file, starting at 1
+	pub line: usize,
+
+	/// The path as written in the file
+	pub path: String,
+}
+
+pub fn parse_swpfile<P: AsRef<Path>>(dir: P, filename: P) -> Result<Swpfile> {
+	let path = dir.as_ref().join(filename);
+	let file = File::open(&path).map_err(|e| Error::io(&path, e))?;
 	let reader = BufReader::new(file);
 
-	let mut paths = Vec::new();
+	let mut swpfile = Swpfile::default();
+
+	let invalid = |line: usize, message: String| Error::Parse {
+		path: path.clone(),
+		line,
+		message,
+	};
 
-	for line in reader.lines() {
-		let line = line?.trim().to_owned();
+	for (i, line) in reader.lines().enumerate() {
+		let line = line.map_err(|e| Error::io(&path, e))?.trim().to_owned();
 
-		if line.len() < 1 {
+		if line.is_empty() {
 			continue;
 		}
 		if line.starts_with('#') {
 			continue;
 		}
-		if paths.contains(&line) {
+		if line.starts_with('@') {
+			parse_directive(&mut swpfile, &line).map_err(|e| invalid(i + 1, e))?;
 			continue;
 		}
 
-		paths.push(line);
+		let (list, path) = match line.strip_prefix('!') {
+			Some(path) => (&mut swpfile.exclusions, path.trim().to_owned()),
+			None => (&mut swpfile.entries, line),
+		};
+
+		if path.is_empty() {
+			return Err(invalid(i + 1, "Missing path after '!'".to_owned()));
+		}
+		if list.iter().any(|e| e.path == path) {
+			continue;
+		}
+		if is_glob(&path) {
+			compile_glob(&path).map_err(|e| invalid(i + 1, e))?;
+		}
+
+		list.push(SwpfileEntry { line: i + 1, path });
+	}
+
+	Ok(swpfile)
+}
+
+/// Parses a line starting with `@`, which configures how the project
+/// should be swept rather than listing a directory
+fn parse_directive(swpfile: &mut Swpfile, line: &str) -> std::result::Result<(), String> {
+	let mut parts = line.splitn(2, char::is_whitespace);
+	let directive = parts.next().unwrap_or("");
+	let value = parts.next().unwrap_or("").trim();
+
+	match directive {
+		"@older-than" => {
+			swpfile.older_than = Some(parse_duration(value)?);
+		}
+		"@defaults" => {
+			if !value.is_empty() {
+				return Err("The @defaults directive does not take a value".to_owned());
+			}
+			swpfile.defaults = true;
+		}
+		_ => return Err(format!("Unknown directive '{}'", directive)),
 	}
 
-	Ok(paths)
+	return Ok(());
 }
 
 #[cfg(test)]
@@ -35,11 +114,11 @@ mod test {
 	#[test]
 	fn empty_file() {
 		test_utils::with_temp_dir(|dir| {
-			std::fs::write(dir.join(".swpfile"), "")
-				.expect("Could not write test file");
+			std::fs::write(dir.join(".swpfile"), "").expect("Could not write test file");
 
 			let dirs = parse_swpfile(dir, Path::new(".swpfile"))
-				.expect("Error while reading .swpfile");
+				.expect("Error while reading .swpfile")
+				.entries;
 
 			assert_eq!(dirs.len(), 0);
 		});
@@ -47,7 +126,7 @@ mod test {
 
 	#[test]
 	fn swpfile() {
-		const FILE_CONTENTS: &'static str = r"
+		const FILE_CONTENTS: &str = r"
 			# comment
 
 			target
@@ -58,26 +137,91 @@ mod test {
 			";
 
 		test_utils::with_temp_dir(|dir| {
-			std::fs::write(dir.join(".swpfile"), FILE_CONTENTS)
-				.expect("Could not write test file");
+			std::fs::write(dir.join(".swpfile"), FILE_CONTENTS).expect("Could not write test file");
 
 			let dirs = parse_swpfile(dir, Path::new(".swpfile"))
-				.expect("Error while reading .swpfile");
+				.expect("Error while reading .swpfile")
+				.entries;
 
 			assert_eq!(dirs.len(), 3);
+			assert_eq!(dirs[0].line, 4);
+			assert_eq!(dirs[1].path, "test-directory/");
 		});
 	}
 
 	#[test]
 	fn swpfile_cleanuprc() {
 		test_utils::with_temp_dir(|dir| {
-			std::fs::write(dir.join(".cleanuprc"), "target")
-				.expect("Could not write test file");
+			std::fs::write(dir.join(".cleanuprc"), "target").expect("Could not write test file");
 
 			let dirs = parse_swpfile(dir, Path::new(".cleanuprc"))
-				.expect("Error while reading .cleanuprc");
+				.expect("Error while reading .cleanuprc")
+				.entries;
 
 			assert_eq!(dirs.len(), 1);
 		});
 	}
+
+	#[test]
+	fn older_than() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(dir.join(".swpfile"), "@older-than 3d\ntarget")
+				.expect("Could not write test file");
+
+			let swpfile =
+				parse_swpfile(dir, Path::new(".swpfile")).expect("Error while reading .swpfile");
+
+			assert_eq!(
+				swpfile.older_than,
+				Some(Duration::from_secs(3 * 24 * 60 * 60))
+			);
+			assert_eq!(swpfile.entries.len(), 1);
+		});
+	}
+
+	#[test]
+	fn exclusions() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(dir.join(".swpfile"), "@defaults\ncoverage\n!build\n! dist/")
+				.expect("Could not write test file");
+
+			let swpfile =
+				parse_swpfile(dir, Path::new(".swpfile")).expect("Error while reading .swpfile");
+
+			assert!(swpfile.defaults);
+			assert_eq!(swpfile.entries.len(), 1);
+			assert_eq!(swpfile.exclusions.len(), 2);
+			assert_eq!(swpfile.exclusions[0].path, "build");
+			assert_eq!(swpfile.exclusions[1].path, "dist/");
+			assert_eq!(swpfile.exclusions[1].line, 4);
+
+			std::fs::write(dir.join(".swpfile"), "!").expect("Could not write test file");
+			assert!(parse_swpfile(dir, Path::new(".swpfile")).is_err());
+		});
+	}
+
+	#[test]
+	fn invalid_pattern() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(dir.join(".swpfile"), "packages/*/node_modules\n[abc")
+				.expect("Could not write test file");
+
+			assert!(matches!(
+				parse_swpfile(dir, Path::new(".swpfile")),
+				Err(Error::Parse { line: 2, .. })
+			));
+		});
+	}
+
+	#[test]
+	fn invalid_directive() {
+		test_utils::with_temp_dir(|dir| {
+			std::fs::write(dir.join(".swpfile"), "@older-than soon")
+				.expect("Could not write test file");
+			assert!(parse_swpfile(dir, Path::new(".swpfile")).is_err());
+
+			std::fs::write(dir.join(".swpfile"), "@unknown").expect("Could not write test file");
+			assert!(parse_swpfile(dir, Path::new(".swpfile")).is_err());
+		});
+	}
 }
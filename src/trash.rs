@@ -0,0 +1,306 @@
+use std::io::Result;
+use std::path::Path;
+
+/// True if directories can be moved to the trash on this platform
+pub const SUPPORTED: bool = cfg!(all(unix, not(target_os = "macos")));
+
+/// Moves a file or directory to the trash instead of deleting it, so it can
+/// be restored from the file manager if needed
+///
+/// This follows the FreeDesktop.org trash specification, which is used by
+/// most Linux and BSD desktop environments. Paths on the same filesystem as
+/// the home directory go to `$XDG_DATA_HOME/Trash`, paths on other mounts go
+/// to the `.Trash/$uid` or `.Trash-$uid` directory at the top of that mount.
+///
+/// # Arguments
+/// `path` - The absolute path to move to the trash
+#[cfg(all(unix, not(target_os = "macos")))]
+pub fn move_to_trash(path: &Path) -> Result<()> {
+	freedesktop::move_to_trash(path)
+}
+
+/// Moves a file or directory to the trash instead of deleting it
+///
+/// Only the FreeDesktop.org trash is supported at this time, so this always
+/// returns an error on other platforms.
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+pub fn move_to_trash(_path: &Path) -> Result<()> {
+	use std::io::{Error, ErrorKind};
+
+	Err(Error::new(
+		ErrorKind::Unsupported,
+		"Moving to the trash is not supported on this platform",
+	))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod freedesktop {
+	use std::env;
+	use std::fs::{self, DirBuilder, OpenOptions};
+	use std::io::{Error, ErrorKind, Result, Write};
+	use std::os::unix::ffi::OsStrExt;
+	use std::os::unix::fs::{DirBuilderExt, MetadataExt, PermissionsExt};
+	use std::path::{Path, PathBuf};
+
+	/// The sticky bit, which must be set on a shared `.Trash` directory
+	const S_ISVTX: u32 = 0o1000;
+
+	pub fn move_to_trash(path: &Path) -> Result<()> {
+		let device = fs::symlink_metadata(path)?.dev();
+
+		if let Some(home_trash) = home_trash_dir() {
+			if device_of_nearest_existing(&home_trash)? == device {
+				return trash_in(path, &home_trash, None);
+			}
+		}
+
+		let top_dir = mount_top_dir(path, device);
+		let trash_dir = top_dir_trash(&top_dir)?;
+		return trash_in(path, &trash_dir, Some(&top_dir));
+	}
+
+	/// Moves a path into the given trash directory
+	///
+	/// # Arguments
+	/// `path`      - The path to move
+	/// `trash_dir` - The trash directory, containing `files` and `info`
+	/// `top_dir`   - The top directory of the mount, for trash directories
+	///               on a mount other than the home directory. The original
+	///               path is stored relative to this directory.
+	pub(super) fn trash_in(path: &Path, trash_dir: &Path, top_dir: Option<&Path>) -> Result<()> {
+		let files_dir = trash_dir.join("files");
+		let info_dir = trash_dir.join("info");
+		create_private_dir(&files_dir)?;
+		create_private_dir(&info_dir)?;
+
+		let original_path = match top_dir.and_then(|top_dir| path.strip_prefix(top_dir).ok()) {
+			Some(relative) => relative,
+			None => path,
+		};
+
+		let file_name = path
+			.file_name()
+			.ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Path has no file name"))?
+			.to_string_lossy()
+			.into_owned();
+
+		// The info file is created first and exclusively, which reserves the
+		// name in the trash even if another application is trashing a file
+		// with the same name at the same time
+		let mut counter = 1;
+		let (trashed_name, info_path, mut info_file) = loop {
+			let name = if counter == 1 {
+				file_name.clone()
+			} else {
+				format!("{}.{}", file_name, counter)
+			};
+			let info_path = info_dir.join(format!("{}.trashinfo", name));
+
+			match OpenOptions::new()
+				.write(true)
+				.create_new(true)
+				.open(&info_path)
+			{
+				Ok(file) if !files_dir.join(&name).exists() => break (name, info_path, file),
+				Ok(_) => {
+					let _ = fs::remove_file(&info_path);
+				}
+				Err(ref e) if e.kind() == ErrorKind::AlreadyExists => (),
+				Err(e) => return Err(e),
+			}
+
+			counter += 1;
+		};
+
+		let info = format!(
+			"[Trash Info]\nPath={}\nDeletionDate={}\n",
+			encode_path(original_path),
+			deletion_date()
+		);
+
+		let result = info_file
+			.write_all(info.as_bytes())
+			.and_then(|_| fs::rename(path, files_dir.join(&trashed_name)));
+
+		if result.is_err() {
+			let _ = fs::remove_file(&info_path);
+		}
+
+		return result;
+	}
+
+	/// Gets the trash directory in the user's home directory
+	fn home_trash_dir() -> Option<PathBuf> {
+		if let Some(dir) = env::var_os("XDG_DATA_HOME").filter(|dir| !dir.is_empty()) {
+			return Some(PathBuf::from(dir).join("Trash"));
+		}
+
+		env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share/Trash"))
+	}
+
+	/// Gets the device of a path, or of its closest existing parent if the
+	/// path doesn't exist yet
+	fn device_of_nearest_existing(path: &Path) -> Result<u64> {
+		for ancestor in path.ancestors() {
+			if let Ok(metadata) = fs::metadata(ancestor) {
+				return Ok(metadata.dev());
+			}
+		}
+
+		Err(Error::new(ErrorKind::NotFound, "Trash directory not found"))
+	}
+
+	/// Finds the top directory of the mount containing the given path
+	fn mount_top_dir(path: &Path, device: u64) -> PathBuf {
+		let mut top_dir = path;
+
+		while let Some(parent) = top_dir.parent() {
+			match fs::metadata(parent) {
+				Ok(metadata) if metadata.dev() == device => top_dir = parent,
+				_ => break,
+			}
+		}
+
+		top_dir.to_owned()
+	}
+
+	/// Gets the trash directory to use at the top of a mount
+	///
+	/// An administrator-provided `$top_dir/.Trash` is used if it exists and is
+	/// safe (a real directory with the sticky bit set), otherwise the trash
+	/// goes into a user-specific `$top_dir/.Trash-$uid` directory.
+	fn top_dir_trash(top_dir: &Path) -> Result<PathBuf> {
+		let uid = unsafe { libc::getuid() };
+
+		let shared = top_dir.join(".Trash");
+		if let Ok(metadata) = fs::symlink_metadata(&shared) {
+			if metadata.is_dir() && metadata.permissions().mode() & S_ISVTX != 0 {
+				let dir = shared.join(uid.to_string());
+				if create_private_dir(&dir).is_ok() {
+					return Ok(dir);
+				}
+			}
+		}
+
+		let dir = top_dir.join(format!(".Trash-{}", uid));
+		create_private_dir(&dir)?;
+		return Ok(dir);
+	}
+
+	/// Creates a directory that is only accessible to the current user, if it
+	/// doesn't exist yet
+	fn create_private_dir(path: &Path) -> Result<()> {
+		DirBuilder::new().recursive(true).mode(0o700).create(path)
+	}
+
+	/// Percent-encodes a path for the `Path` key in a `.trashinfo` file
+	pub(super) fn encode_path(path: &Path) -> String {
+		let mut encoded = String::new();
+
+		for byte in path.as_os_str().as_bytes() {
+			match byte {
+				b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+					encoded.push(*byte as char)
+				}
+				_ => encoded.push_str(&format!("%{:02X}", byte)),
+			}
+		}
+
+		return encoded;
+	}
+
+	/// Gets the current local time in the format used by `.trashinfo` files,
+	/// e.g. `2004-08-31T22:32:08`
+	fn deletion_date() -> String {
+		unsafe {
+			let now = libc::time(std::ptr::null_mut());
+			let mut tm: libc::tm = std::mem::zeroed();
+			libc::localtime_r(&now, &mut tm);
+
+			format!(
+				"{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+				tm.tm_year + 1900,
+				tm.tm_mon + 1,
+				tm.tm_mday,
+				tm.tm_hour,
+				tm.tm_min,
+				tm.tm_sec
+			)
+		}
+	}
+}
+
+#[cfg(all(test, unix, not(target_os = "macos")))]
+mod test {
+	use std::fs;
+	use std::path::Path;
+
+	use super::freedesktop::{encode_path, trash_in};
+	use crate::utils::test_utils;
+
+	#[test]
+	fn encode() {
+		assert_eq!(encode_path(Path::new("/home/me/a b")), "/home/me/a%20b");
+		assert_eq!(encode_path(Path::new("/tmp/ü")), "/tmp/%C3%BC");
+	}
+
+	#[test]
+	fn trash_directory() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "project");
+			test_utils::create_dir(dir, "project/node_modules");
+			test_utils::create_file(dir, "project/node_modules/index.js");
+
+			let trash = dir.join("Trash");
+			trash_in(&dir.join("project/node_modules"), &trash, None)
+				.expect("Could not move directory to trash");
+
+			assert!(!dir.join("project/node_modules").exists());
+			assert!(trash.join("files/node_modules/index.js").exists());
+
+			let info = fs::read_to_string(trash.join("info/node_modules.trashinfo"))
+				.expect("Could not read trashinfo file");
+			assert!(info.starts_with("[Trash Info]\n"));
+			assert!(info.contains(&format!(
+				"\nPath={}\n",
+				encode_path(&dir.join("project/node_modules"))
+			)));
+			assert!(info.contains("\nDeletionDate="));
+		});
+	}
+
+	#[test]
+	fn trash_duplicate_name() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "a/build");
+			test_utils::create_dir(dir, "b");
+			test_utils::create_dir(dir, "b/build");
+
+			let trash = dir.join("Trash");
+			trash_in(&dir.join("a/build"), &trash, None)
+				.expect("Could not move directory to trash");
+			trash_in(&dir.join("b/build"), &trash, None)
+				.expect("Could not move directory to trash");
+
+			assert!(trash.join("files/build").is_dir());
+			assert!(trash.join("files/build.2").is_dir());
+			assert!(trash.join("info/build.2.trashinfo").is_file());
+		});
+	}
+
+	#[test]
+	fn trash_relative_to_top_dir() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "target");
+
+			let trash = dir.join(".Trash-1000");
+			trash_in(&dir.join("target"), &trash, Some(dir))
+				.expect("Could not move directory to trash");
+
+			let info = fs::read_to_string(trash.join("info/target.trashinfo"))
+				.expect("Could not read trashinfo file");
+			assert!(info.contains("\nPath=target\n"));
+		});
+	}
+}
@@ -0,0 +1,27 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Tells long-running operations to stop, e.g. when the user presses Ctrl-C
+///
+/// Clones of a token share the same state, so a token can be cancelled from
+/// a signal handler while the worker threads check it.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+	cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+	pub fn new() -> CancellationToken {
+		CancellationToken::default()
+	}
+
+	/// Asks all operations using this token to stop
+	pub fn cancel(&self) {
+		self.cancelled.store(true, Ordering::SeqCst);
+	}
+
+	/// Checks if the operations using this token should stop
+	pub fn is_cancelled(&self) -> bool {
+		self.cancelled.load(Ordering::SeqCst)
+	}
+}
@@ -0,0 +1,128 @@
+use std::collections::HashMap;
+use std::io::Result;
+use std::path::Path;
+use std::sync::{OnceLock, RwLock};
+
+use globset::GlobMatcher;
+
+use crate::utils::glob::{compile_glob, is_glob};
+
+/// The names of all entries in a directory, read once so several marker
+/// files can be checked without a separate syscall for each of them
+#[derive(Debug)]
+pub struct DirListing {
+	/// Names of the entries in the directory, sorted alphabetically
+	names: Vec<String>,
+}
+
+impl DirListing {
+	/// Reads the entries of a directory
+	///
+	/// # Arguments
+	/// `path` - The directory to read
+	pub fn read(path: &Path) -> Result<DirListing> {
+		let mut names: Vec<String> = path
+			.read_dir()?
+			.filter_map(|e| e.ok())
+			.filter_map(|e| e.file_name().into_string().ok())
+			.collect();
+
+		names.sort();
+		return Ok(DirListing { names });
+	}
+
+	/// Checks if the directory contains an entry with the given name
+	pub fn contains(&self, name: &str) -> bool {
+		self.names
+			.binary_search_by(|n| n.as_str().cmp(name))
+			.is_ok()
+	}
+
+	/// Finds the first entry that matches a name or glob pattern
+	///
+	/// # Arguments
+	/// `pattern` - A file name like `Cargo.toml`, or a pattern like `*.csproj`
+	///
+	/// # Returns
+	/// The name of the first matching entry in alphabetical order
+	pub fn find(&self, pattern: &str) -> Option<&str> {
+		self.find_all(pattern).into_iter().next()
+	}
+
+	/// Finds all entries that match a name or glob pattern
+	///
+	/// # Arguments
+	/// `pattern` - A file name like `Cargo.toml`, or a pattern like `*.csproj`
+	///
+	/// # Returns
+	/// The names of the matching entries, sorted alphabetically
+	pub fn find_all(&self, pattern: &str) -> Vec<&str> {
+		if !is_glob(pattern) {
+			return match self.names.binary_search_by(|n| n.as_str().cmp(pattern)) {
+				Ok(i) => vec![self.names[i].as_str()],
+				Err(_) => Vec::new(),
+			};
+		}
+
+		let glob = match cached_glob(pattern) {
+			Some(glob) => glob,
+			None => return Vec::new(),
+		};
+
+		return self
+			.names
+			.iter()
+			.filter(|name| glob.is_match(name))
+			.map(|name| name.as_str())
+			.collect();
+	}
+}
+
+/// Compiles a glob pattern, reusing it if it was compiled before
+///
+/// Marker patterns are checked against every directory that is searched, so
+/// compiling them again each time would add up quickly.
+fn cached_glob(pattern: &str) -> Option<GlobMatcher> {
+	static CACHE: OnceLock<RwLock<HashMap<String, Option<GlobMatcher>>>> = OnceLock::new();
+	let cache = CACHE.get_or_init(|| RwLock::new(HashMap::new()));
+
+	if let Some(glob) = cache.read().unwrap().get(pattern) {
+		return glob.clone();
+	}
+
+	let glob = compile_glob(pattern).ok();
+	cache
+		.write()
+		.unwrap()
+		.insert(pattern.to_owned(), glob.clone());
+	return glob;
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::utils::test_utils;
+
+	#[test]
+	fn find_markers() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_file(dir, "Cargo.toml");
+			test_utils::create_file(dir, "Web.csproj");
+			test_utils::create_file(dir, "Api.csproj");
+			test_utils::create_dir(dir, "App.Tests.csproj");
+
+			let listing = DirListing::read(dir).expect("Could not read directory");
+
+			assert!(listing.contains("Cargo.toml"));
+			assert!(!listing.contains("package.json"));
+			assert_eq!(listing.find("Cargo.toml"), Some("Cargo.toml"));
+			assert_eq!(listing.find("package.json"), None);
+			assert_eq!(listing.find("*.csproj"), Some("Api.csproj"));
+			assert_eq!(
+				listing.find_all("*.csproj"),
+				vec!["Api.csproj", "App.Tests.csproj", "Web.csproj"]
+			);
+			assert_eq!(listing.find("*.sln"), None);
+		});
+	}
+}
@@ -0,0 +1,246 @@
+use std::cmp;
+use std::collections::HashSet;
+use std::fs::{self, Metadata};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use crossbeam::queue::SegQueue;
+
+use crate::error::Result;
+use crate::utils::{process_queue, CancellationToken};
+use crate::Event;
+
+/// Calculates the disk space used by each of the given directories, or
+/// files
+///
+/// Sizes are based on the allocated blocks on disk rather than the apparent
+/// file size, so they reflect the space that will be freed when deleting the
+/// directory. Files with several hard links are only counted once, even if
+/// the links are spread over several of the given directories. Symlinks are
+/// not followed.
+///
+/// # Arguments
+/// `dirs`     - The directories or files to measure
+/// `cancel`   - Stops measuring when cancelled, leaving the sizes incomplete
+/// `on_event` - Called with `Event::Measuring` for each directory that's
+///              read, and `Event::DirMeasured` for each of `dirs` once it's
+///              measured completely
+///
+/// # Returns
+/// The size of each directory in bytes, in the same order as `dirs`, or an
+/// error if measuring stopped unexpectedly. Files that can't be read are not
+/// counted.
+pub fn dir_sizes<F>(dirs: &[PathBuf], cancel: &CancellationToken, on_event: F) -> Result<Vec<u64>>
+where
+	F: Sync + Fn(Event),
+{
+	let sizes: Vec<AtomicU64> = dirs.iter().map(|_| AtomicU64::new(0)).collect();
+	let seen_inodes = Mutex::new(HashSet::new());
+
+	// Number of directories inside each of `dirs` that are still queued or
+	// being read. Subdirectories are counted before their parent is done, so
+	// this only reaches 0 when the entire directory has been measured.
+	let pending: Vec<AtomicUsize> = dirs.iter().map(|_| AtomicUsize::new(0)).collect();
+
+	let queue = SegQueue::new();
+	for (i, dir) in dirs.iter().enumerate() {
+		match fs::symlink_metadata(dir) {
+			Ok(ref metadata) if !metadata.is_dir() => {
+				if is_unique(metadata, &seen_inodes) {
+					sizes[i].fetch_add(disk_usage(metadata), Ordering::SeqCst);
+				}
+
+				on_event(Event::DirMeasured {
+					path: dir,
+					size: sizes[i].load(Ordering::SeqCst),
+				});
+			}
+			_ => {
+				pending[i].fetch_add(1, Ordering::SeqCst);
+				queue.push((i, dir.clone()));
+			}
+		}
+	}
+
+	process_queue(
+		cmp::max(8, num_cpus::get() * 2),
+		&queue,
+		cancel,
+		|(i, path)| {
+			let mut bytes = 0;
+
+			if let Ok(entries) = path.read_dir() {
+				for entry in entries.filter_map(|e| e.ok()) {
+					// `DirEntry::metadata` does not follow symlinks
+					let metadata = match entry.metadata() {
+						Ok(metadata) => metadata,
+						Err(_) => continue,
+					};
+
+					if is_unique(&metadata, &seen_inodes) {
+						bytes += disk_usage(&metadata);
+					}
+
+					if metadata.is_dir() {
+						pending[i].fetch_add(1, Ordering::SeqCst);
+						queue.push((i, entry.path()));
+					}
+				}
+			}
+
+			sizes[i].fetch_add(bytes, Ordering::SeqCst);
+			on_event(Event::Measuring { path: &path, bytes });
+
+			if pending[i].fetch_sub(1, Ordering::SeqCst) == 1 {
+				on_event(Event::DirMeasured {
+					path: &dirs[i],
+					size: sizes[i].load(Ordering::SeqCst),
+				});
+			}
+		},
+	)?;
+
+	return Ok(sizes.into_iter().map(|s| s.into_inner()).collect());
+}
+
+/// Gets the space a file takes up on disk
+#[cfg(unix)]
+fn disk_usage(metadata: &Metadata) -> u64 {
+	use std::os::unix::fs::MetadataExt;
+
+	// `blocks()` is always expressed in 512-byte units, regardless of the
+	// block size of the filesystem
+	metadata.blocks() * 512
+}
+
+/// Gets the space a file takes up on disk
+#[cfg(not(unix))]
+fn disk_usage(metadata: &Metadata) -> u64 {
+	metadata.len()
+}
+
+/// Checks if a file should be counted, or if another hard link to the same
+/// file has already been counted
+#[cfg(unix)]
+fn is_unique(metadata: &Metadata, seen_inodes: &Mutex<HashSet<(u64, u64)>>) -> bool {
+	use std::os::unix::fs::MetadataExt;
+
+	if metadata.is_dir() || metadata.nlink() <= 1 {
+		return true;
+	}
+
+	seen_inodes
+		.lock()
+		.unwrap()
+		.insert((metadata.dev(), metadata.ino()))
+}
+
+/// Checks if a file should be counted, or if another hard link to the same
+/// file has already been counted
+#[cfg(not(unix))]
+fn is_unique(_metadata: &Metadata, _seen_inodes: &Mutex<HashSet<(u64, u64)>>) -> bool {
+	true
+}
+
+#[cfg(test)]
+mod test {
+	use std::fs;
+	use std::sync::Mutex;
+
+	use super::dir_sizes;
+	use crate::utils::test_utils;
+	use crate::utils::CancellationToken;
+	use crate::Event;
+
+	#[test]
+	fn empty_dir() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "empty");
+
+			let sizes = dir_sizes(&[dir.join("empty")], &CancellationToken::new(), |_| ()).unwrap();
+			assert_eq!(sizes, vec![0]);
+		});
+	}
+
+	#[test]
+	fn nested_files() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "a/b");
+			fs::write(dir.join("a/b/file"), vec![1; 10_000]).expect("Could not write test file");
+
+			let sizes = dir_sizes(&[dir.join("a")], &CancellationToken::new(), |_| ()).unwrap();
+			assert!(sizes[0] >= 10_000, "Nested file was not counted");
+		});
+	}
+
+	#[test]
+	fn progress() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "a/b");
+			test_utils::create_dir(dir, "a/b/c");
+			test_utils::create_dir(dir, "d");
+			fs::write(dir.join("a/b/file"), vec![1; 10_000]).expect("Could not write test file");
+			fs::write(dir.join("file"), vec![1; 10_000]).expect("Could not write test file");
+
+			let measured = Mutex::new(0);
+			let finished = Mutex::new(Vec::new());
+			let dirs = [dir.join("a"), dir.join("d"), dir.join("file")];
+			let sizes = dir_sizes(&dirs, &CancellationToken::new(), |event| match event {
+				Event::Measuring { bytes, .. } => *measured.lock().unwrap() += bytes,
+				Event::DirMeasured { path, size } => {
+					finished.lock().unwrap().push((path.to_owned(), size))
+				}
+				_ => panic!("Unexpected event {:?}", event),
+			})
+			.unwrap();
+
+			let mut finished = finished.into_inner().unwrap();
+			finished.sort();
+			assert_eq!(
+				finished,
+				vec![
+					(dirs[0].clone(), sizes[0]),
+					(dirs[1].clone(), sizes[1]),
+					(dirs[2].clone(), sizes[2]),
+				]
+			);
+			assert_eq!(measured.into_inner().unwrap(), sizes[0] + sizes[1]);
+		});
+	}
+
+	#[test]
+	fn single_file() {
+		test_utils::with_temp_dir(|dir| {
+			fs::write(dir.join("file"), vec![1; 10_000]).expect("Could not write test file");
+
+			let sizes = dir_sizes(&[dir.join("file")], &CancellationToken::new(), |_| ()).unwrap();
+			assert!(sizes[0] >= 10_000, "File was not counted");
+		});
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn hard_links() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "b");
+			fs::write(dir.join("a/file"), vec![1; 100_000]).expect("Could not write test file");
+			fs::hard_link(dir.join("a/file"), dir.join("b/file"))
+				.expect("Could not create hard link");
+
+			let sizes = dir_sizes(
+				&[dir.join("a"), dir.join("b")],
+				&CancellationToken::new(),
+				|_| (),
+			)
+			.unwrap();
+			// Either directory may be measured first, so only the total is predictable
+			let total = sizes[0] + sizes[1];
+			assert!(total >= 100_000, "Hard linked file was not counted");
+			assert!(total < 200_000, "Hard linked file was counted twice");
+		});
+	}
+}
@@ -0,0 +1,172 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const MINUTE: u64 = 60;
+const HOUR: u64 = 60 * MINUTE;
+const DAY: u64 = 24 * HOUR;
+const WEEK: u64 = 7 * DAY;
+const MONTH: u64 = 30 * DAY;
+const YEAR: u64 = 365 * DAY;
+
+/// Parses a human-readable duration like `7d`, `2w` or `6months`
+///
+/// Several parts can be combined, e.g. `1w 3d`. A month is counted as 30
+/// days and a year as 365 days. A bare `m` is rejected because it could mean
+/// either minutes or months.
+///
+/// # Arguments
+/// `input` - The duration to parse
+///
+/// # Returns
+/// The parsed duration, or a message describing why it could not be parsed
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+	let input = input.trim();
+	if input.is_empty() {
+		return Err("Duration cannot be empty".to_owned());
+	}
+
+	let mut total = 0;
+	let mut chars = input.chars().peekable();
+
+	while chars.peek().is_some() {
+		let mut number = String::new();
+		while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+			number.push(*c);
+			chars.next();
+		}
+
+		while chars.peek().filter(|c| c.is_whitespace()).is_some() {
+			chars.next();
+		}
+
+		let mut unit = String::new();
+		while let Some(c) = chars.peek().filter(|c| c.is_alphabetic()) {
+			unit.push(c.to_ascii_lowercase());
+			chars.next();
+		}
+
+		while chars.peek().filter(|c| c.is_whitespace()).is_some() {
+			chars.next();
+		}
+
+		if number.is_empty() {
+			return Err(format!("Invalid duration '{}', expected a number", input));
+		}
+
+		let number: u64 = number
+			.parse()
+			.map_err(|_| format!("Invalid duration '{}', number is too large", input))?;
+
+		let multiplier = match unit.as_str() {
+			"s" | "sec" | "secs" | "second" | "seconds" => 1,
+			"min" | "mins" | "minute" | "minutes" => MINUTE,
+			"h" | "hr" | "hrs" | "hour" | "hours" => HOUR,
+			"d" | "day" | "days" => DAY,
+			"w" | "wk" | "wks" | "week" | "weeks" => WEEK,
+			"mo" | "month" | "months" => MONTH,
+			"y" | "yr" | "yrs" | "year" | "years" => YEAR,
+			"m" => {
+				return Err(format!(
+					"Ambiguous unit in duration '{}', use 'min' for minutes or 'mo' for months",
+					input
+				))
+			}
+			"" => return Err(format!("Missing unit in duration '{}'", input)),
+			unit => return Err(format!("Unknown unit '{}' in duration '{}'", unit, input)),
+		};
+
+		total = number
+			.checked_mul(multiplier)
+			.and_then(|n| n.checked_add(total))
+			.ok_or_else(|| format!("Invalid duration '{}', number is too large", input))?;
+	}
+
+	return Ok(Duration::from_secs(total));
+}
+
+/// Formats a duration in the largest whole unit that fits, e.g. `30 days`
+pub fn format_duration(duration: Duration) -> String {
+	let secs = duration.as_secs();
+
+	let units = [
+		(YEAR, "year"),
+		(WEEK, "week"),
+		(DAY, "day"),
+		(HOUR, "hour"),
+		(MINUTE, "minute"),
+	];
+
+	for (size, name) in units.iter() {
+		let count = secs / size;
+		if count > 0 && count * size == secs {
+			return if count == 1 {
+				format!("1 {}", name)
+			} else {
+				format!("{} {}s", count, name)
+			};
+		}
+	}
+
+	if secs == 1 {
+		"1 second".to_owned()
+	} else {
+		format!("{} seconds", secs)
+	}
+}
+
+/// Describes how long ago a Unix timestamp was in days, e.g. `3 days ago`
+pub fn format_age(timestamp: u64) -> String {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_secs())
+		.unwrap_or(0);
+
+	match now.saturating_sub(timestamp) / DAY {
+		0 => "today".to_owned(),
+		1 => "1 day ago".to_owned(),
+		days => format!("{} days ago", days),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use std::time::Duration;
+
+	use super::*;
+
+	#[test]
+	fn parse_units() {
+		assert_eq!(parse_duration("7d"), Ok(Duration::from_secs(7 * DAY)));
+		assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(2 * WEEK)));
+		assert_eq!(
+			parse_duration("6months"),
+			Ok(Duration::from_secs(6 * MONTH))
+		);
+		assert_eq!(parse_duration("90 days"), Ok(Duration::from_secs(90 * DAY)));
+		assert_eq!(parse_duration("1y"), Ok(Duration::from_secs(YEAR)));
+		assert_eq!(parse_duration("12h"), Ok(Duration::from_secs(12 * HOUR)));
+	}
+
+	#[test]
+	fn parse_combined() {
+		assert_eq!(parse_duration("1w 3d"), Ok(Duration::from_secs(10 * DAY)));
+		assert_eq!(parse_duration("1d12h"), Ok(Duration::from_secs(36 * HOUR)));
+	}
+
+	#[test]
+	fn parse_invalid() {
+		assert!(parse_duration("").is_err());
+		assert!(parse_duration("7").is_err());
+		assert!(parse_duration("d").is_err());
+		assert!(parse_duration("3m").is_err());
+		assert!(parse_duration("3 fortnights").is_err());
+		assert!(parse_duration("-3d").is_err());
+	}
+
+	#[test]
+	fn format() {
+		assert_eq!(format_duration(Duration::from_secs(30 * DAY)), "30 days");
+		assert_eq!(format_duration(Duration::from_secs(3 * DAY)), "3 days");
+		assert_eq!(format_duration(Duration::from_secs(2 * WEEK)), "2 weeks");
+		assert_eq!(format_duration(Duration::from_secs(90)), "90 seconds");
+	}
+}
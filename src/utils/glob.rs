@@ -0,0 +1,314 @@
+use std::path::{Path, PathBuf};
+
+use globset::{GlobBuilder, GlobMatcher};
+
+/// Directories that are never searched when expanding a pattern
+const ALWAYS_SKIP_DIRS: [&str; 1] = [".git"];
+
+/// Checks if a path contains any glob syntax
+///
+/// # Arguments
+/// `pattern` - The path or pattern to check
+///
+/// # Returns
+/// True if the path contains `*`, `?` or `[`
+pub fn is_glob(pattern: &str) -> bool {
+	pattern.contains(['*', '?', '['])
+}
+
+/// Compiles a gitignore-style glob pattern
+///
+/// `*` and `?` match within a single path component, `**` matches any number
+/// of directories and `[abc]` matches a character class. Like in a
+/// `.gitignore`, a pattern without a `/` matches at any depth, while a
+/// pattern with a leading or middle `/` is anchored to the directory it is
+/// expanded in. A trailing `/` is ignored, and `{` and `}` have no special
+/// meaning.
+///
+/// # Arguments
+/// `pattern` - The pattern to compile
+///
+/// # Returns
+/// The compiled pattern, or a message describing why it's invalid
+pub fn compile_glob(pattern: &str) -> Result<GlobMatcher, String> {
+	return compile_anchored_glob(&anchor(pattern));
+}
+
+/// Compiles a glob pattern that is always relative to the directory it is
+/// matched against, even if it doesn't contain a `/`
+///
+/// This is the syntax of `compile_glob` without the gitignore rules for
+/// where a pattern matches, for patterns like Cargo workspace members.
+///
+/// # Arguments
+/// `pattern` - The pattern to compile
+///
+/// # Returns
+/// The compiled pattern, or a message describing why it's invalid
+pub fn compile_anchored_glob(pattern: &str) -> Result<GlobMatcher, String> {
+	let pattern = escape_braces(pattern.trim_matches('/'));
+
+	GlobBuilder::new(&pattern)
+		.literal_separator(true)
+		.backslash_escape(true)
+		.build()
+		.map(|glob| glob.compile_matcher())
+		.map_err(|e| e.to_string())
+}
+
+/// Rewrites a gitignore-style pattern so it's relative to the directory it
+/// is expanded in, e.g. `*.egg-info` becomes `**/*.egg-info` and
+/// `/build` becomes `build`
+fn anchor(pattern: &str) -> String {
+	let pattern = pattern.trim_end_matches('/');
+
+	if let Some(anchored) = pattern.strip_prefix('/') {
+		return anchored.to_owned();
+	}
+	if pattern.contains('/') {
+		return pattern.to_owned();
+	}
+
+	return format!("**/{}", pattern);
+}
+
+/// Escapes `{` and `}` outside of character classes, so they're matched
+/// literally instead of as alternatives like `{a,b}`
+fn escape_braces(pattern: &str) -> String {
+	let mut escaped = String::with_capacity(pattern.len());
+	let mut in_class = false;
+	let mut chars = pattern.chars();
+
+	while let Some(c) = chars.next() {
+		match c {
+			'\\' => {
+				// Already escaped, so the next character is kept as it is
+				escaped.push(c);
+				escaped.extend(chars.next());
+				continue;
+			}
+			'[' => in_class = true,
+			']' => in_class = false,
+			'{' | '}' if !in_class => escaped.push('\\'),
+			_ => (),
+		}
+
+		escaped.push(c);
+	}
+
+	return escaped;
+}
+
+/// Finds all directories inside `root` that match a glob pattern
+///
+/// Matched directories are not searched any further, and symlinks are not
+/// followed. Unless the pattern contains `**`, the search stops at the depth
+/// of the pattern, so simple patterns like `packages/*/node_modules` don't
+/// need to walk the entire directory tree.
+///
+/// # Arguments
+/// `root`    - The directory to search
+/// `pattern` - The compiled pattern
+/// `source`  - The pattern as passed to `compile_glob`, used to determine
+///             the search depth
+/// `skip`    - Called for each directory before it's searched, return true to
+///             skip the directory and everything in it
+///
+/// # Returns
+/// The matched directories, sorted by path
+pub fn expand_glob<F: Fn(&Path) -> bool>(
+	root: &Path,
+	pattern: &GlobMatcher,
+	source: &str,
+	skip: F,
+) -> Vec<PathBuf> {
+	let source = anchor(source);
+	let max_depth = if source.contains("**") {
+		usize::MAX
+	} else {
+		source.split('/').count()
+	};
+
+	let mut matched = Vec::new();
+	let mut stack = vec![(root.to_owned(), 0)];
+
+	while let Some((dir, depth)) = stack.pop() {
+		let entries = match dir.read_dir() {
+			Ok(entries) => entries,
+			Err(_) => continue,
+		};
+
+		for entry in entries.filter_map(|e| e.ok()) {
+			// `DirEntry::file_type` does not follow symlinks
+			if !entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+				continue;
+			}
+
+			let path = entry.path();
+			if ALWAYS_SKIP_DIRS.contains(&entry.file_name().to_str().unwrap_or("")) || skip(&path) {
+				continue;
+			}
+
+			let relative = match path.strip_prefix(root) {
+				Ok(relative) => relative,
+				Err(_) => continue,
+			};
+
+			if pattern.is_match(relative) {
+				matched.push(path);
+			} else if depth + 1 < max_depth {
+				stack.push((path, depth + 1));
+			}
+		}
+	}
+
+	matched.sort();
+	return matched;
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::utils::test_utils;
+
+	fn expand(root: &Path, pattern: &str) -> Vec<PathBuf> {
+		let glob = compile_glob(pattern).expect("Invalid pattern");
+		expand_glob(root, &glob, pattern, |_| false)
+	}
+
+	#[test]
+	fn glob_detection() {
+		assert!(is_glob("packages/*/node_modules"));
+		assert!(is_glob("**/__pycache__"));
+		assert!(is_glob("build-?"));
+		assert!(is_glob("[ab]"));
+		assert!(!is_glob("target"));
+		assert!(!is_glob("android/app/build"));
+		assert!(!is_glob("{a,b}"));
+	}
+
+	#[test]
+	fn anchoring() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "app.egg-info");
+			test_utils::create_dir(dir, "src");
+			test_utils::create_dir(dir, "src/lib.egg-info");
+			test_utils::create_dir(dir, "src/build-a");
+
+			// Without a slash, a pattern matches at any depth
+			assert_eq!(
+				expand(dir, "*.egg-info"),
+				vec![dir.join("app.egg-info"), dir.join("src/lib.egg-info")]
+			);
+
+			// A leading or middle slash anchors it to the root
+			assert_eq!(expand(dir, "/*.egg-info"), vec![dir.join("app.egg-info")]);
+			assert_eq!(
+				expand(dir, "src/*.egg-info"),
+				vec![dir.join("src/lib.egg-info")]
+			);
+			assert_eq!(expand(dir, "/build-?"), Vec::<PathBuf>::new());
+		});
+	}
+
+	#[test]
+	fn no_alternatives() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "{a,b}");
+			test_utils::create_dir(dir, "{c}");
+
+			assert_eq!(expand(dir, "/{a,b}*"), vec![dir.join("{a,b}")]);
+			assert_eq!(expand(dir, "/[{]c}"), vec![dir.join("{c}")]);
+		});
+	}
+
+	#[test]
+	fn anchored_glob() {
+		let glob = compile_anchored_glob("crates*").expect("Invalid pattern");
+		assert!(glob.is_match("crates-a"));
+		assert!(!glob.is_match("nested/crates-a"));
+	}
+
+	#[test]
+	fn invalid_pattern() {
+		assert!(compile_glob("[abc").is_err());
+	}
+
+	#[test]
+	fn single_star() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "packages");
+			test_utils::create_dir(dir, "packages/a");
+			test_utils::create_dir(dir, "packages/a/node_modules");
+			test_utils::create_dir(dir, "packages/b");
+			test_utils::create_dir(dir, "packages/b/src");
+			test_utils::create_dir(dir, "packages/b/src/node_modules");
+
+			assert_eq!(
+				expand(dir, "packages/*/node_modules"),
+				vec![dir.join("packages/a/node_modules")]
+			);
+		});
+	}
+
+	#[test]
+	fn double_star() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "__pycache__");
+			test_utils::create_dir(dir, "src");
+			test_utils::create_dir(dir, "src/__pycache__");
+			test_utils::create_dir(dir, "src/lib");
+			test_utils::create_dir(dir, "src/lib/__pycache__");
+			test_utils::create_dir(dir, "src/lib/__pycache__/nested");
+			test_utils::create_dir(dir, "src/lib/__pycache__/nested/__pycache__");
+
+			assert_eq!(
+				expand(dir, "**/__pycache__"),
+				vec![
+					dir.join("__pycache__"),
+					dir.join("src/__pycache__"),
+					dir.join("src/lib/__pycache__"),
+				]
+			);
+		});
+	}
+
+	#[test]
+	fn character_class() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "build-a");
+			test_utils::create_dir(dir, "build-b");
+			test_utils::create_dir(dir, "build-c");
+			test_utils::create_file(dir, "build-d");
+
+			assert_eq!(
+				expand(dir, "/build-[ab]"),
+				vec![dir.join("build-a"), dir.join("build-b")]
+			);
+			assert_eq!(
+				expand(dir, "build-?"),
+				vec![
+					dir.join("build-a"),
+					dir.join("build-b"),
+					dir.join("build-c")
+				]
+			);
+		});
+	}
+
+	#[test]
+	fn skip_dirs() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "a");
+			test_utils::create_dir(dir, "a/cache");
+			test_utils::create_dir(dir, "b");
+			test_utils::create_dir(dir, "b/cache");
+
+			let glob = compile_glob("**/cache").expect("Invalid pattern");
+			let matched = expand_glob(dir, &glob, "**/cache", |path| path == dir.join("a"));
+
+			assert_eq!(matched, vec![dir.join("b/cache")]);
+		});
+	}
+}
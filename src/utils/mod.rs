@@ -1,7 +1,17 @@
+mod cancel;
+mod dir_listing;
+mod dir_size;
+pub mod duration;
 pub mod file_utils;
+pub mod glob;
 mod process_queue;
+mod remove_all;
 
+pub use self::cancel::CancellationToken;
+pub use self::dir_listing::DirListing;
+pub use self::dir_size::dir_sizes;
 pub use self::process_queue::process_queue;
+pub use self::remove_all::{remove_all, RemoveErrors};
 
 #[cfg(test)]
 pub mod test_utils;
@@ -1,17 +1,19 @@
-use std::thread;
+use std::sync::{Condvar, Mutex};
 use std::time::Duration;
 
 use crossbeam::queue::SegQueue;
 use crossbeam::scope;
 
-/// Since the queue may be empty at one point but new paths could get added
-/// by another thread right after, every thread should try the queue several
-/// times before terminating.
-const MAX_TRIES: usize = 5;
+use super::CancellationToken;
+use crate::error::{Error, Result};
 
-/// Time a thread should wait in milliseconds before each retry, after it
-/// receives an empty queue
-const TIMEOUT_MS_BETWEEN_TRIES: u64 = 50;
+/// How long an idle thread waits before checking the queue again
+///
+/// Idle threads are woken up as soon as another thread finishes an entry,
+/// but entries can also be added while an entry is still being handled. This
+/// makes sure those are picked up without waiting for the slow entry to
+/// finish.
+const IDLE_POLL_INTERVAL: Duration = Duration::from_millis(10);
 
 /// Process a queue on multiple threads
 ///
@@ -19,74 +21,123 @@ const TIMEOUT_MS_BETWEEN_TRIES: u64 = 50;
 /// it's meant to be used for IO-heavy operations or other tasks that have a
 /// similarly high wait time.
 ///
+/// Entries may add new entries to the queue while they're being handled.
+/// The function returns once the queue is empty and no entries are being
+/// handled anymore, so no entries can be added after it returns.
+///
+/// When cancelled, the entries that are being handled are finished but no
+/// new entries are taken from the queue, so it may not be empty afterwards.
+///
+/// If handling an entry panics, the other threads keep processing the queue
+/// and an error is returned at the end.
+///
 /// # Arguments
 /// `num_threads` - The number of threads to spawn
 /// `queue`       - The queue to process
+/// `cancel`      - Stops processing the queue when cancelled
 /// `on_entry`    - Called for each entry in the queue
 ///                 Argument is the entry
-/// `on_retry`    - Called before a worker thread retries because the queue is empty
-///                 Argument is the number of retries that have been attempted
 ///
 /// # Example
-/// ```rust
+/// ```rust,ignore
 /// let queue = SegQueue::new();
 /// queue.push(7);
 /// queue.push(42);
 ///
 /// // This call will print `7` and `42` in any order
-/// process_queue(
-///     4,
-///     &queue,
-///     |num| println!("{}", num),
-///     |tries| print!()
-/// );
+/// process_queue(4, &queue, &CancellationToken::new(), |num| println!("{}", num))?;
 /// ```
-pub fn process_queue<F1, F2, T>(num_threads: usize, queue: &SegQueue<T>, on_entry: F1, on_retry: F2)
+pub fn process_queue<F, T>(
+	num_threads: usize,
+	queue: &SegQueue<T>,
+	cancel: &CancellationToken,
+	on_entry: F,
+) -> Result<()>
 where
-	F1: Sync + Fn(T),
-	F2: Sync + Fn(usize),
+	F: Sync + Fn(T),
 	T: Send,
 {
 	// No use in spawning all these threads and processing an empty queue
-	if queue.len() == 0 {
-		return;
+	if queue.is_empty() {
+		return Ok(());
 	}
 
+	// Number of entries that are being handled. Only while this is more than
+	// 0 can new entries be added to an empty queue.
+	let in_flight = Mutex::new(0usize);
+	let finished = Condvar::new();
+
 	// Crossbeam scoped threads
 	scope(|s| {
 		for _ in 0..num_threads {
-			s.spawn(|_| {
-				let mut tries = 0;
+			s.spawn(|_| loop {
+				let entry = {
+					let mut in_flight = in_flight.lock().unwrap();
+					loop {
+						if cancel.is_cancelled() {
+							return;
+						}
 
-				while tries < MAX_TRIES {
-					// If there's an entry in the queue, handle it
-					if let Ok(entry) = queue.pop() {
-						tries = 0;
+						// Taking an entry and counting it happens while
+						// holding the lock, so other threads never see an
+						// empty queue without seeing the entry in flight
+						if let Ok(entry) = queue.pop() {
+							*in_flight += 1;
+							break entry;
+						}
 
-						on_entry(entry);
-						continue;
-					}
+						if *in_flight == 0 {
+							return;
+						}
 
-					// If no entry was found, sleep for a little while so
-					// the other threads get a chance to put something new
-					// in the queue
-					tries += 1;
-					on_retry(tries);
+						in_flight = finished
+							.wait_timeout(in_flight, IDLE_POLL_INTERVAL)
+							.unwrap()
+							.0;
+					}
+				};
 
-					thread::sleep(Duration::from_millis(TIMEOUT_MS_BETWEEN_TRIES));
-				}
+				// Marks the entry as finished even if handling it panics, so
+				// the other threads don't wait for it forever
+				let _guard = InFlightGuard {
+					in_flight: &in_flight,
+					finished: &finished,
+				};
+				on_entry(entry);
 			});
 		}
 	})
-	.expect("Threading error"); // TODO add better error handling
+	.map_err(|_| Error::Thread)
+}
+
+/// Marks an entry as finished when dropped, and wakes up the idle threads
+/// so they can pick up the entries it added or terminate
+struct InFlightGuard<'a> {
+	in_flight: &'a Mutex<usize>,
+	finished: &'a Condvar,
+}
+
+impl Drop for InFlightGuard<'_> {
+	fn drop(&mut self) {
+		let mut in_flight = match self.in_flight.lock() {
+			Ok(in_flight) => in_flight,
+			Err(poisoned) => poisoned.into_inner(),
+		};
+		*in_flight -= 1;
+		self.finished.notify_all();
+	}
 }
 
 #[cfg(test)]
 mod test {
 	use crossbeam::queue::SegQueue;
 	use std::sync::atomic::{AtomicUsize, Ordering};
+	use std::thread;
+	use std::time::{Duration, Instant};
 
 	use super::process_queue;
+	use crate::error::Error;
+	use crate::utils::CancellationToken;
 
 	#[test]
 	fn count_items() {
@@ -97,21 +148,114 @@ mod test {
 
 		let total = AtomicUsize::new(0);
 
-		process_queue(
-			4,
-			&queue,
-			|i| {
-				total.fetch_add(i, Ordering::SeqCst);
-			},
-			|_| (),
-		);
-
-		let total = total.into_inner();
-
-		// We don't need to test the inner workings of `process_queue()`
-		// since the heavy lifting is handled by the `crossbeam` crate.
-		// So the only thing we should check is if the entire queue has
-		// been processed.
-		assert_eq!(total, 190);
+		process_queue(4, &queue, &CancellationToken::new(), |i| {
+			total.fetch_add(i, Ordering::SeqCst);
+		})
+		.unwrap();
+
+		assert_eq!(total.into_inner(), 190);
+	}
+
+	#[test]
+	fn slow_producers() {
+		// Each entry takes longer than the old retry timeout before it adds
+		// more entries, so idle threads must wait for it instead of exiting
+		let queue: SegQueue<usize> = SegQueue::new();
+		queue.push(0);
+
+		let handled = AtomicUsize::new(0);
+
+		process_queue(4, &queue, &CancellationToken::new(), |depth| {
+			handled.fetch_add(1, Ordering::SeqCst);
+
+			if depth < 3 {
+				thread::sleep(Duration::from_millis(300));
+				queue.push(depth + 1);
+				queue.push(depth + 1);
+			}
+		})
+		.unwrap();
+
+		assert_eq!(handled.into_inner(), 15);
+		assert!(queue.is_empty());
+	}
+
+	#[test]
+	fn single_slow_producer() {
+		// All other threads are idle while the only entry is being handled,
+		// and its children are added one by one
+		let queue: SegQueue<usize> = SegQueue::new();
+		queue.push(0);
+
+		let handled = AtomicUsize::new(0);
+
+		process_queue(8, &queue, &CancellationToken::new(), |i| {
+			handled.fetch_add(1, Ordering::SeqCst);
+
+			if i == 0 {
+				for child in 1..=5 {
+					thread::sleep(Duration::from_millis(100));
+					queue.push(child);
+				}
+			}
+		})
+		.unwrap();
+
+		assert_eq!(handled.into_inner(), 6);
+	}
+
+	#[test]
+	fn terminates_immediately() {
+		let queue: SegQueue<usize> = SegQueue::new();
+		queue.push(1);
+
+		let start = Instant::now();
+		process_queue(8, &queue, &CancellationToken::new(), |_| ()).unwrap();
+
+		// The old implementation always waited 250 ms before returning
+		assert!(start.elapsed() < Duration::from_millis(200));
+	}
+
+	#[test]
+	fn cancel() {
+		let queue: SegQueue<usize> = SegQueue::new();
+		queue.push(0);
+
+		let cancel = CancellationToken::new();
+		let handled = AtomicUsize::new(0);
+
+		// Every entry adds another one, so this would never finish if it
+		// weren't cancelled
+		process_queue(4, &queue, &cancel, |i| {
+			if handled.fetch_add(1, Ordering::SeqCst) == 10 {
+				cancel.cancel();
+			}
+			queue.push(i + 1);
+		})
+		.unwrap();
+
+		assert!(handled.into_inner() >= 11);
+		assert!(!queue.is_empty());
+	}
+
+	#[test]
+	fn panic() {
+		let queue: SegQueue<usize> = SegQueue::new();
+		for i in 0..10 {
+			queue.push(i);
+		}
+
+		let handled = AtomicUsize::new(0);
+
+		// The other entries are still handled after one of them panics
+		let result = process_queue(2, &queue, &CancellationToken::new(), |i| {
+			if i == 0 {
+				panic!("Test panic");
+			}
+			handled.fetch_add(1, Ordering::SeqCst);
+		});
+
+		assert!(matches!(result, Err(Error::Thread)));
+		assert_eq!(handled.into_inner(), 9);
 	}
 }
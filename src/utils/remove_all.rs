@@ -0,0 +1,382 @@
+use std::cmp::Reverse;
+use std::fmt;
+use std::fs::{self, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Everything that went wrong while removing a directory
+#[derive(Debug, Default)]
+pub struct RemoveErrors {
+	/// Files and directories that couldn't be removed because they belong
+	/// to another user, so their permissions can't be changed
+	pub not_owned: Vec<PathBuf>,
+
+	/// Other files and directories that couldn't be removed
+	pub failed: Vec<(PathBuf, io::Error)>,
+}
+
+impl RemoveErrors {
+	pub fn is_empty(&self) -> bool {
+		self.not_owned.is_empty() && self.failed.is_empty()
+	}
+
+	/// Groups the failed files and directories by the kind of error, e.g.
+	/// permission denied or resource busy
+	///
+	/// # Returns
+	/// Each kind of error along with the files that failed that way, with
+	/// the most common kind first
+	pub fn by_kind(&self) -> Vec<(io::ErrorKind, Vec<&(PathBuf, io::Error)>)> {
+		let mut groups: Vec<(io::ErrorKind, Vec<&(PathBuf, io::Error)>)> = Vec::new();
+		for failure in &self.failed {
+			let kind = failure.1.kind();
+			match groups.iter_mut().find(|(group, _)| *group == kind) {
+				Some((_, failures)) => failures.push(failure),
+				None => groups.push((kind, vec![failure])),
+			}
+		}
+
+		// The sort is stable, so kinds that are equally common stay in the
+		// order they occured
+		groups.sort_by_key(|(_, failures)| Reverse(failures.len()));
+		return groups;
+	}
+}
+
+/// Describes a kind of error, e.g. `permission denied`
+///
+/// # Arguments
+/// `kind`  - The kind of error
+/// `error` - An error of that kind, which is used if the kind itself
+///           doesn't say anything useful
+fn describe_kind(kind: io::ErrorKind, error: &io::Error) -> String {
+	if kind == io::ErrorKind::Other {
+		return error.to_string();
+	}
+
+	return kind.to_string();
+}
+
+impl fmt::Display for RemoveErrors {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let mut parts = Vec::new();
+
+		for (kind, failures) in self.by_kind() {
+			let (path, error) = failures[0];
+			let mut part = format!("{}: {}", describe_kind(kind, error), path.display());
+			if failures.len() > 1 {
+				part = format!("{} and {} more", part, failures.len() - 1);
+			}
+
+			parts.push(part);
+		}
+
+		match self.not_owned.len() {
+			0 => (),
+			1 => parts.push("1 file is owned by another user".to_owned()),
+			n => parts.push(format!("{} files are owned by another user", n)),
+		}
+
+		write!(f, "{}", parts.join(", "))
+	}
+}
+
+/// Removes a file or directory and everything in it
+///
+/// Unlike `std::fs::remove_dir_all`, this handles read-only directories
+/// (like the Go module cache) and read-only files by making them writable
+/// first, as long as they belong to the current user. Removal continues
+/// when something can't be removed, so as much as possible is cleaned up.
+///
+/// Symlinks are removed, but never followed, so nothing outside the
+/// directory is ever touched.
+///
+/// # Arguments
+/// `path` - The file or directory to remove
+///
+/// # Returns
+/// Everything that couldn't be removed, if anything
+pub fn remove_all(path: &Path) -> Result<(), RemoveErrors> {
+	let mut errors = RemoveErrors::default();
+	remove_recursive(path, &mut errors);
+
+	if errors.is_empty() {
+		return Ok(());
+	} else {
+		return Err(errors);
+	}
+}
+
+/// Removes a path, recording any errors
+///
+/// # Returns
+/// True if the path was removed
+fn remove_recursive(path: &Path, errors: &mut RemoveErrors) -> bool {
+	let metadata = match fs::symlink_metadata(path) {
+		Ok(metadata) => metadata,
+		Err(ref e) if e.kind() == io::ErrorKind::NotFound => return true,
+		Err(e) => {
+			errors.failed.push((path.to_owned(), e));
+			return false;
+		}
+	};
+
+	if !metadata.is_dir() {
+		return remove_entry(path, &metadata, |p| fs::remove_file(p), errors);
+	}
+
+	// Entries can only be removed from a directory that is writable, and
+	// listed if it's readable
+	if is_owned(&metadata) && !is_accessible(&metadata) {
+		if let Err(e) = make_accessible(path, &metadata) {
+			errors.failed.push((path.to_owned(), e));
+			return false;
+		}
+	}
+
+	let entries = match fs::read_dir(path) {
+		Ok(entries) => entries,
+		Err(ref e) if e.kind() == io::ErrorKind::PermissionDenied && !is_owned(&metadata) => {
+			add_not_owned(path, errors);
+			return false;
+		}
+		Err(e) => {
+			errors.failed.push((path.to_owned(), e));
+			return false;
+		}
+	};
+
+	let mut removed_all = true;
+	for entry in entries {
+		match entry {
+			Ok(entry) => removed_all &= remove_recursive(&entry.path(), errors),
+			Err(e) => {
+				errors.failed.push((path.to_owned(), e));
+				removed_all = false;
+			}
+		}
+	}
+
+	// The directory can't be removed while it still contains something,
+	// and that has already been reported
+	if !removed_all {
+		return false;
+	}
+
+	return remove_entry(path, &metadata, |p| fs::remove_dir(p), errors);
+}
+
+/// Removes a file, symlink or empty directory, making it writable first if
+/// that's what stops it from being removed
+///
+/// # Returns
+/// True if the path was removed
+fn remove_entry(
+	path: &Path,
+	metadata: &Metadata,
+	remove: fn(&Path) -> io::Result<()>,
+	errors: &mut RemoveErrors,
+) -> bool {
+	let error = match remove(path) {
+		Ok(()) => return true,
+		Err(e) => e,
+	};
+
+	if error.kind() != io::ErrorKind::PermissionDenied {
+		errors.failed.push((path.to_owned(), error));
+		return false;
+	}
+
+	if !is_owned(metadata) {
+		add_not_owned(path, errors);
+		return false;
+	}
+
+	// On Unix, entries are removed from their parent directory, so that's
+	// the one that needs to be writable rather than the entry itself
+	#[cfg(unix)]
+	{
+		if let Some(parent) = path.parent() {
+			if let Ok(parent_metadata) = fs::symlink_metadata(parent) {
+				if !is_owned(&parent_metadata) {
+					add_not_owned(parent, errors);
+					return false;
+				}
+
+				if make_accessible(parent, &parent_metadata).is_ok() && remove(path).is_ok() {
+					return true;
+				}
+			}
+		}
+	}
+
+	// On Windows, read-only files and directories can't be removed. Symlinks
+	// are never changed, since that could change their target instead.
+	#[cfg(windows)]
+	{
+		if !metadata.file_type().is_symlink() && metadata.permissions().readonly() {
+			let mut permissions = metadata.permissions();
+			#[allow(clippy::permissions_set_readonly_false)]
+			permissions.set_readonly(false);
+			if fs::set_permissions(path, permissions).is_ok() && remove(path).is_ok() {
+				return true;
+			}
+		}
+	}
+
+	errors.failed.push((path.to_owned(), error));
+	return false;
+}
+
+/// Records a path that belongs to another user, unless it was recorded
+/// already because several of its entries couldn't be removed
+fn add_not_owned(path: &Path, errors: &mut RemoveErrors) {
+	if !errors.not_owned.iter().any(|p| p == path) {
+		errors.not_owned.push(path.to_owned());
+	}
+}
+
+/// Checks if the owner of a directory can list and remove its contents
+#[cfg(unix)]
+fn is_accessible(metadata: &Metadata) -> bool {
+	use std::os::unix::fs::PermissionsExt;
+	return metadata.permissions().mode() & 0o700 == 0o700;
+}
+
+/// Read-only directories can still be listed and have their entries
+/// removed on other platforms
+#[cfg(not(unix))]
+fn is_accessible(_metadata: &Metadata) -> bool {
+	return true;
+}
+
+/// Makes a directory readable, writable and searchable by its owner
+#[cfg(unix)]
+fn make_accessible(path: &Path, metadata: &Metadata) -> io::Result<()> {
+	use std::os::unix::fs::PermissionsExt;
+	let mut permissions = metadata.permissions();
+	permissions.set_mode(permissions.mode() | 0o700);
+	return fs::set_permissions(path, permissions);
+}
+
+#[cfg(not(unix))]
+fn make_accessible(_path: &Path, _metadata: &Metadata) -> io::Result<()> {
+	return Ok(());
+}
+
+/// Checks if a file belongs to the current user, which means its
+/// permissions can be changed. Root can change the permissions of any file.
+#[cfg(unix)]
+fn is_owned(metadata: &Metadata) -> bool {
+	use std::os::unix::fs::MetadataExt;
+	let uid = unsafe { libc::geteuid() };
+	return uid == 0 || metadata.uid() == uid;
+}
+
+#[cfg(not(unix))]
+fn is_owned(_metadata: &Metadata) -> bool {
+	return true;
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::utils::test_utils;
+
+	#[test]
+	fn remove_tree() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "target");
+			test_utils::create_dir(dir, "target/debug");
+			test_utils::create_file(dir, "target/debug/app");
+			test_utils::create_file(dir, "target/CACHEDIR.TAG");
+
+			remove_all(&dir.join("target")).expect("Could not remove directory");
+			assert!(!dir.join("target").exists());
+
+			// Removing something that doesn't exist is not an error
+			remove_all(&dir.join("target")).expect("Could not remove directory");
+		});
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn read_only() {
+		use std::os::unix::fs::PermissionsExt;
+
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "mod");
+			test_utils::create_dir(dir, "mod/example.com@v1.0.0");
+			test_utils::create_file(dir, "mod/example.com@v1.0.0/go.mod");
+
+			let read_only = fs::Permissions::from_mode(0o555);
+			fs::set_permissions(dir.join("mod/example.com@v1.0.0/go.mod"), read_only.clone())
+				.unwrap();
+			fs::set_permissions(dir.join("mod/example.com@v1.0.0"), read_only.clone()).unwrap();
+			fs::set_permissions(dir.join("mod"), read_only).unwrap();
+
+			remove_all(&dir.join("mod")).expect("Could not remove directory");
+			assert!(!dir.join("mod").exists());
+		});
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn symlinks() {
+		test_utils::with_temp_dir(|dir| {
+			test_utils::create_dir(dir, "outside");
+			test_utils::create_file(dir, "outside/keep");
+			test_utils::create_dir(dir, "node_modules");
+			std::os::unix::fs::symlink(dir.join("outside"), dir.join("node_modules/link")).unwrap();
+
+			remove_all(&dir.join("node_modules")).expect("Could not remove directory");
+			assert!(!dir.join("node_modules").exists());
+			assert!(dir.join("outside/keep").exists());
+		});
+	}
+
+	fn permission_denied() -> io::Error {
+		return io::Error::from(io::ErrorKind::PermissionDenied);
+	}
+
+	#[test]
+	fn by_kind() {
+		let errors = RemoveErrors {
+			not_owned: Vec::new(),
+			failed: vec![
+				(PathBuf::from("a"), io::Error::from(io::ErrorKind::NotFound)),
+				(PathBuf::from("b"), permission_denied()),
+				(PathBuf::from("c"), permission_denied()),
+			],
+		};
+
+		let groups: Vec<(io::ErrorKind, usize)> = errors
+			.by_kind()
+			.iter()
+			.map(|(kind, failures)| (*kind, failures.len()))
+			.collect();
+		assert_eq!(
+			groups,
+			vec![
+				(io::ErrorKind::PermissionDenied, 2),
+				(io::ErrorKind::NotFound, 1)
+			]
+		);
+	}
+
+	#[test]
+	fn display() {
+		let errors = RemoveErrors {
+			not_owned: vec![PathBuf::from("a"), PathBuf::from("b")],
+			failed: vec![
+				(PathBuf::from("c"), io::Error::other("Busy")),
+				(PathBuf::from("d"), permission_denied()),
+				(PathBuf::from("e"), permission_denied()),
+			],
+		};
+		assert_eq!(
+			errors.to_string(),
+			"permission denied: d and 1 more, Busy: c, 2 files are owned by another user"
+		);
+	}
+}